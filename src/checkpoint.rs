@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use ::rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use crate::structs::Model;
+use crate::util::file_stem;
+
+const CHECKPOINT_VERSION: u32 = 1;
+pub(crate) const CHECKPOINT_EXTENSION: &str = "checkpoint";
+
+// Seeded generator for everything random that happens while a model runs; its position in the stream is
+// saved with checkpoints so a resumed run draws the same numbers
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(into = "RngState", try_from = "RngState")]
+pub(crate) struct SimulationRng(ChaCha8Rng);
+
+// Written as hexadecimal strings, as TOML integers stop at i64
+#[derive(Deserialize, Serialize)]
+struct RngState {
+    seed: String,
+    stream: String,
+    word_pos: String,
+}
+
+impl SimulationRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        SimulationRng(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl RngCore for SimulationRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, destination: &mut [u8]) {
+        self.0.fill_bytes(destination)
+    }
+
+    fn try_fill_bytes(&mut self, destination: &mut [u8]) -> Result<(), ::rand::Error> {
+        self.0.try_fill_bytes(destination)
+    }
+}
+
+impl From<SimulationRng> for RngState {
+    fn from(rng: SimulationRng) -> Self {
+        RngState {
+            seed: rng.0.get_seed().iter().map(|byte| format!("{:02x}", byte)).collect(),
+            stream: format!("{:x}", rng.0.get_stream()),
+            word_pos: format!("{:x}", rng.0.get_word_pos()),
+        }
+    }
+}
+
+impl TryFrom<RngState> for SimulationRng {
+    type Error = String;
+
+    fn try_from(state: RngState) -> Result<Self, String> {
+        let invalid = |field: &str| format!("invalid random number generator {}", field);
+        if state.seed.len() != 64 || !state.seed.is_ascii() {
+            return Err(invalid("seed"));
+        }
+        let mut seed = [0; 32];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&state.seed[2 * i..2 * i + 2], 16).map_err(|_| invalid("seed"))?;
+        }
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        rng.set_stream(u64::from_str_radix(&state.stream, 16).map_err(|_| invalid("stream"))?);
+        rng.set_word_pos(u128::from_str_radix(&state.word_pos, 16).map_err(|_| invalid("word_pos"))?);
+        Ok(SimulationRng(rng))
+    }
+}
+
+// Every field of the model, including the Verlet previous positions, last forces, spacecraft burns already
+// executed and the random number generator, so that a resumed run continues bit for bit
+#[derive(Deserialize, Serialize)]
+struct Checkpoint {
+    version: u32,
+    model: Model,
+}
+
+// Written to a temporary file first, so an interruption never leaves a partial checkpoint behind
+pub(crate) fn save_checkpoint(model: &Model, path: &Path) -> Result<(), String> {
+    let error = |reason: String| format!("could not write checkpoint {}: {}", path.display(), reason);
+    let checkpoint = Checkpoint { version: CHECKPOINT_VERSION, model: model.clone() };
+    let text = toml::to_string(&checkpoint).map_err(|reason| error(reason.to_string()))?;
+    if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+        fs::create_dir_all(directory).map_err(|reason| error(reason.to_string()))?;
+    }
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, text).map_err(|reason| error(reason.to_string()))?;
+    fs::rename(&temporary, path).map_err(|reason| error(reason.to_string()))
+}
+
+pub(crate) fn load_checkpoint(path: &Path) -> Result<Model, String> {
+    let error = |reason: String| format!("could not read checkpoint {}: {}", path.display(), reason);
+    let text = fs::read_to_string(path).map_err(|reason| error(reason.to_string()))?;
+    let checkpoint: Checkpoint = toml::from_str(&text).map_err(|reason| error(reason.to_string()))?;
+    if checkpoint.version != CHECKPOINT_VERSION {
+        return Err(error(format!("unsupported version {}", checkpoint.version)));
+    }
+    Ok(checkpoint.model)
+}
+
+// Most recently written checkpoint of the named model in the directory
+pub(crate) fn latest_checkpoint(directory: &Path, name: &str) -> Option<PathBuf> {
+    let prefix = format!("{}-", file_stem(name));
+    fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let path = entry.path();
+            path.extension().is_some_and(|extension| extension == CHECKPOINT_EXTENSION)
+                && path.file_name().is_some_and(|file| file.to_string_lossy().starts_with(&prefix))
+        })
+        .max_by_key(|entry| entry.metadata().and_then(|metadata| metadata.modified()).ok())
+        .map(|entry| entry.path())
+}
+
+#[cfg(test)]
+mod tests {
+    use ::rand::Rng;
+    use super::*;
+    use crate::models::{lunar_transfer, solar_system};
+
+    fn assert_identical(a: &Model, b: &Model) {
+        assert_eq!(a.steps, b.steps);
+        assert_eq!(a.elapsed_time.to_bits(), b.elapsed_time.to_bits());
+        assert_eq!(a.particles.len(), b.particles.len());
+        for (p, q) in a.particles.iter().zip(&b.particles) {
+            for k in 0..2 {
+                assert_eq!(p.position[k].to_bits(), q.position[k].to_bits(), "{} position", p.name);
+                assert_eq!(p.previous_position[k].to_bits(), q.previous_position[k].to_bits(), "{} previous position", p.name);
+                assert_eq!(p.force[k].to_bits(), q.force[k].to_bits(), "{} force", p.name);
+            }
+            assert_eq!(p.mass.to_bits(), q.mass.to_bits(), "{} mass", p.name);
+        }
+    }
+
+    // Runs a model straight through, and again with a checkpoint written and read back halfway
+    fn check_resume(mut model: Model, steps: u64) {
+        let _: f64 = model.rng.gen();
+        let path = std::env::temp_dir().join(format!("{}-{}.{}", file_stem(&model.name), std::process::id(), CHECKPOINT_EXTENSION));
+        let mut uninterrupted = model.clone();
+        for _ in 0..steps {
+            uninterrupted.step();
+        }
+        let draw: u64 = uninterrupted.rng.gen();
+
+        for _ in 0..steps / 2 {
+            model.step();
+        }
+        save_checkpoint(&model, &path).unwrap();
+        drop(model);
+        let mut resumed = load_checkpoint(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        for _ in 0..steps - steps / 2 {
+            resumed.step();
+        }
+
+        assert_identical(&uninterrupted, &resumed);
+        assert_eq!(draw, resumed.rng.gen::<u64>());
+    }
+
+    #[test]
+    fn resumed_solar_system_is_bit_identical() {
+        let mut model = solar_system();
+        model.relativistic = true;
+        check_resume(model, 2000);
+    }
+
+    #[test]
+    fn resumed_spacecraft_is_bit_identical() {
+        // Halfway falls during the finite mid-course burn, after the injection impulse
+        check_resume(lunar_transfer(), 3002);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::structs::Model;
+use crate::units::{Quantity, UnitSystem};
+
+const MINUTE: f64 = 60.0;                       // seconds
+const DAY: f64 = 86400.0;                       // seconds
+const JULIAN_YEAR: f64 = 365.25 * DAY;          // seconds
+
+// A span of seconds in the largest units that suit it, such as "2 y 143 d" or "5 d 04:12:09"
+pub(crate) fn format_duration(seconds: f64) -> String {
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    if seconds < MINUTE {
+        format!("{}{:.2} s", sign, seconds)
+    } else if seconds < DAY {
+        let whole = seconds.floor() as u64;
+        format!("{}{:02}:{:02}:{:02}", sign, whole / 3600, whole / 60 % 60, whole % 60)
+    } else if seconds < JULIAN_YEAR {
+        let whole = seconds.floor() as u64;
+        format!("{}{} d {:02}:{:02}:{:02}", sign, whole / 86400, whole / 3600 % 24, whole / 60 % 60, whole % 60)
+    } else if seconds < 1e4 * JULIAN_YEAR {
+        let years = (seconds / JULIAN_YEAR).floor();
+        format!("{}{} y {:.1} d", sign, years, (seconds - years * JULIAN_YEAR) / DAY)
+    } else {
+        format!("{}{:.4e} years", sign, seconds / JULIAN_YEAR)
+    }
+}
+
+// Calendar date and time of a Julian date (Meeus, Astronomical Algorithms ch. 7); Julian calendar before 1582 October 15
+pub(crate) fn calendar_date(julian_date: f64) -> String {
+    let julian_date = (julian_date * DAY).round() / DAY + 0.5;
+    let z = julian_date.floor();
+    let fraction = julian_date - z;
+    let a = if z < 2299161.0 {
+        z
+    } else {
+        let alpha = ((z - 1867216.25) / 36524.25).floor();
+        z + 1.0 + alpha - (alpha / 4.0).floor()
+    };
+    let b = a + 1524.0;
+    let c = ((b - 122.1) / 365.25).floor();
+    let d = (365.25 * c).floor();
+    let e = ((b - d) / 30.6001).floor();
+
+    let day = b - d - (30.6001 * e).floor();
+    let month = if e < 14.0 { e - 1.0 } else { e - 13.0 };
+    let year = if month > 2.0 { c - 4716.0 } else { c - 4715.0 };
+    let seconds = ((fraction * DAY).round() as u64).min(86399);
+    format!("{}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+// Simulated time for the HUD: a duration for models in SI or astronomical units, N-body or model time otherwise
+pub(crate) fn elapsed_text(model: &Model) -> String {
+    match model.units {
+        UnitSystem::Si | UnitSystem::Astronomical => format_duration(model.elapsed_time),
+        UnitSystem::Henon { .. } | UnitSystem::Model => format!("t = {}", model.units.format(model.elapsed_time, Quantity::Time)),
+    }
+}
+
+// Julian date (TDB) the model has reached, if it has a start epoch
+pub(crate) fn current_julian_date(model: &Model) -> Option<f64> {
+    model.epoch.map(|epoch| epoch + model.elapsed_time / DAY)
+}
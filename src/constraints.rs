@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use crate::structs::Particle;
+use crate::util::*;
+
+// Connections between two particles, referenced by their index in the model
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum Constraint {
+    Spring {
+        a: usize,
+        b: usize,
+        rest_length: f64,           // meters
+        stiffness: f64,             // N/m
+        damping: f64,               // N s/m, along the spring axis
+    },
+    Rod {
+        a: usize,
+        b: usize,
+        length: f64,                // meters, held fixed
+    },
+}
+
+// Checks the values serde cannot, e.g. a negative stiffness; the particle indices are checked by the scenario
+pub(crate) fn validate_constraint(constraint: &Constraint) -> Result<(), String> {
+    match constraint {
+        Constraint::Spring { rest_length, stiffness, damping, .. } => {
+            if !([rest_length, stiffness, damping].iter().all(|value| value.is_finite() && **value >= 0.0)) {
+                return Err("rest_length, stiffness and damping must not be negative".into());
+            }
+        }
+        Constraint::Rod { length, .. } => {
+            if !(length.is_finite() && *length > 0.0) {
+                return Err("length must be a positive number".into());
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Constraint {
+    pub(crate) fn particles(&self) -> (usize, usize) {
+        match self {
+            Constraint::Spring { a, b, .. } => (*a, *b),
+            Constraint::Rod { a, b, .. } => (*a, *b),
+        }
+    }
+
+    // Force on particle a from a damped spring; particle b receives the opposite force
+    pub(crate) fn spring_force(&self, particles: &[Particle], delta_t: f64) -> [f64; 2] {
+        let Constraint::Spring { a, b, rest_length, stiffness, damping } = self else {
+            return [0.0, 0.0];
+        };
+        let length = distance(particles[*a].position, particles[*b].position);
+        if length == 0.0 {
+            return [0.0, 0.0];
+        }
+        let normal = [
+            (particles[*b].position[0] - particles[*a].position[0]) / length,
+            (particles[*b].position[1] - particles[*a].position[1]) / length,
+        ];
+        let velocity_a = particles[*a].velocity(delta_t);
+        let velocity_b = particles[*b].velocity(delta_t);
+        let separation_speed = (velocity_b[0] - velocity_a[0]) * normal[0] + (velocity_b[1] - velocity_a[1]) * normal[1];
+
+        let magnitude = stiffness * (length - rest_length) + damping * separation_speed;
+        [magnitude * normal[0], magnitude * normal[1]]
+    }
+
+    // Moves the ends of a rod back to its length, heavier particles moving less
+    pub(crate) fn enforce(&self, particles: &mut [Particle]) {
+        let Constraint::Rod { a, b, length } = self else {
+            return;
+        };
+        let current_length = distance(particles[*a].position, particles[*b].position);
+        if current_length == 0.0 {
+            return;
+        }
+        let inverse_mass_a = particles[*a].inverse_mass();
+        let inverse_mass_b = particles[*b].inverse_mass();
+        let total_inverse_mass = inverse_mass_a + inverse_mass_b;
+        if total_inverse_mass == 0.0 {
+            return;
+        }
+
+        let correction = (current_length - length) / current_length;
+        let separation = [
+            particles[*b].position[0] - particles[*a].position[0],
+            particles[*b].position[1] - particles[*a].position[1],
+        ];
+        let share_a = correction * inverse_mass_a / total_inverse_mass;
+        let share_b = correction * inverse_mass_b / total_inverse_mass;
+        particles[*a].position[0] += separation[0] * share_a;
+        particles[*a].position[1] += separation[1] * share_a;
+        particles[*b].position[0] -= separation[0] * share_b;
+        particles[*b].position[1] -= separation[1] * share_b;
+    }
+}
@@ -0,0 +1,184 @@
+use std::f64::consts::PI;
+use crate::structs::Model;
+
+const SECONDS_PER_CENTURY: f64 = 36525.0 * 86400.0;
+const ARCSECONDS_PER_RADIAN: f64 = 180.0 / PI * 3600.0;
+
+// Measures the precession of a body's periapsis around a primary from its eccentricity vector
+pub(crate) struct PerihelionTracker {
+    pub(crate) body: usize,                 // index of the orbiting particle
+    pub(crate) primary: usize,              // index of the central particle
+    last_angle: Option<f64>,                // radians, wrapped
+    unwrapped_angle: f64,                   // radians, continuous
+    // running moments for the least-squares fit of angle against time
+    samples: f64,
+    mean_time: f64,
+    mean_angle: f64,
+    time_variance: f64,
+    covariance: f64,
+}
+
+impl PerihelionTracker {
+    pub(crate) fn new(body: usize, primary: usize) -> Self {
+        PerihelionTracker {
+            body,
+            primary,
+            last_angle: None,
+            unwrapped_angle: 0.0,
+            samples: 0.0,
+            mean_time: 0.0,
+            mean_angle: 0.0,
+            time_variance: 0.0,
+            covariance: 0.0,
+        }
+    }
+
+    // Tracks the innermost body orbiting the dominant mass, e.g. Mercury around the Sun
+    pub(crate) fn for_model(model: &Model) -> Option<Self> {
+        let primary = model.dominant_particle()?;
+        let position = model.particles[primary].position;
+        let body = (0..model.particles.len())
+            .filter(|&i| i != primary)
+            .min_by(|&a, &b| {
+                let distance_a = crate::util::distance(model.particles[a].position, position);
+                let distance_b = crate::util::distance(model.particles[b].position, position);
+                distance_a.total_cmp(&distance_b)
+            })?;
+        Some(PerihelionTracker::new(body, primary))
+    }
+
+    // Gravitational parameter, eccentricity vector and semi-major axis of the current relative orbit
+    fn orbit(&self, model: &Model) -> Option<(f64, [f64; 2], f64)> {
+        if self.body >= model.particles.len() || self.primary >= model.particles.len() {
+            return None;
+        }
+        let body = &model.particles[self.body];
+        let primary = &model.particles[self.primary];
+        let mu = model.g_constant * (primary.mass + body.mass);
+
+        let r = [body.position[0] - primary.position[0], body.position[1] - primary.position[1]];
+        let body_velocity = body.velocity(model.delta_t);
+        let primary_velocity = primary.velocity(model.delta_t);
+        let v = [body_velocity[0] - primary_velocity[0], body_velocity[1] - primary_velocity[1]];
+
+        let distance = (r[0].powi(2) + r[1].powi(2)).sqrt();
+        let speed_squared = v[0].powi(2) + v[1].powi(2);
+        let radial_velocity = r[0] * v[0] + r[1] * v[1];
+        let eccentricity_vector = [
+            ((speed_squared - mu / distance) * r[0] - radial_velocity * v[0]) / mu,
+            ((speed_squared - mu / distance) * r[1] - radial_velocity * v[1]) / mu,
+        ];
+        let semi_major_axis = 1.0 / (2.0 / distance - speed_squared / mu);
+        Some((mu, eccentricity_vector, semi_major_axis))
+    }
+
+    pub(crate) fn record(&mut self, model: &Model) {
+        let time = model.elapsed_time;
+        let Some((_, eccentricity_vector, _)) = self.orbit(model) else {
+            return;
+        };
+        let angle = eccentricity_vector[1].atan2(eccentricity_vector[0]);
+
+        if let Some(last_angle) = self.last_angle {
+            let mut change = angle - last_angle;
+            if change > PI {
+                change -= 2.0 * PI;
+            } else if change < -PI {
+                change += 2.0 * PI;
+            }
+            self.unwrapped_angle += change;
+        } else {
+            self.unwrapped_angle = angle;
+        }
+        self.last_angle = Some(angle);
+
+        self.samples += 1.0;
+        let time_offset = time - self.mean_time;
+        self.mean_time += time_offset / self.samples;
+        self.mean_angle += (self.unwrapped_angle - self.mean_angle) / self.samples;
+        self.time_variance += time_offset * (time - self.mean_time);
+        self.covariance += time_offset * (self.unwrapped_angle - self.mean_angle);
+    }
+
+    // Least-squares slope of the periapsis angle, radians per second
+    pub(crate) fn rate(&self) -> Option<f64> {
+        if self.samples < 2.0 || self.time_variance == 0.0 {
+            return None;
+        }
+        Some(self.covariance / self.time_variance)
+    }
+
+    pub(crate) fn arcseconds_per_century(&self) -> Option<f64> {
+        self.rate().map(|rate| rate * SECONDS_PER_CENTURY * ARCSECONDS_PER_RADIAN)
+    }
+
+    // 1PN prediction for the current orbit: 6 pi G M / (c^2 a (1 - e^2)) per revolution
+    pub(crate) fn predicted_arcseconds_per_century(&self, model: &Model) -> Option<f64> {
+        let (mu, eccentricity_vector, semi_major_axis) = self.orbit(model)?;
+        if semi_major_axis <= 0.0 {
+            return None;
+        }
+        let eccentricity_squared = eccentricity_vector[0].powi(2) + eccentricity_vector[1].powi(2);
+        let period = 2.0 * PI * (semi_major_axis.powi(3) / mu).sqrt();
+        let shift_per_orbit = 6.0 * PI * mu / (model.speed_of_light.powi(2) * semi_major_axis * (1.0 - eccentricity_squared));
+        Some(shift_per_orbit / period * SECONDS_PER_CENTURY * ARCSECONDS_PER_RADIAN)
+    }
+}
+
+// Separates the relativistic precession from numerical and Newtonian precession by integrating
+// a Newtonian twin of the model in lockstep and comparing the two periapsis rates
+// The twin is a copy of the model when the diagnostic is made, so it is rebuilt whenever the model is edited
+pub(crate) struct PrecessionDiagnostic {
+    pub(crate) tracker: PerihelionTracker,
+    newtonian: Option<(Model, PerihelionTracker)>,
+}
+
+impl PrecessionDiagnostic {
+    pub(crate) fn for_model(model: &Model) -> Option<Self> {
+        let tracker = PerihelionTracker::for_model(model)?;
+        let newtonian = if model.relativistic {
+            let mut twin = model.clone();
+            twin.relativistic = false;
+            Some((twin, PerihelionTracker::new(tracker.body, tracker.primary)))
+        } else {
+            None
+        };
+        Some(PrecessionDiagnostic { tracker, newtonian })
+    }
+
+    pub(crate) fn record(&mut self, model: &Model) {
+        self.tracker.record(model);
+        if let Some((twin, twin_tracker)) = self.newtonian.as_mut() {
+            twin.step();
+            twin_tracker.record(twin);
+        }
+    }
+
+    // Measured precession in excess of the Newtonian twin, arcseconds per century
+    pub(crate) fn relativistic_arcseconds_per_century(&self) -> Option<f64> {
+        let (_, twin_tracker) = self.newtonian.as_ref()?;
+        Some(self.tracker.arcseconds_per_century()? - twin_tracker.arcseconds_per_century()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mercury_precession;
+
+    #[test]
+    fn mercury_precession_matches_first_post_newtonian_prediction() {
+        let mut model = mercury_precession();
+        let mut diagnostic = PrecessionDiagnostic::for_model(&model).unwrap();
+        // Twenty orbits of Mercury, enough for the fit to average out the swing of the osculating periapsis
+        let steps = (20.0 * 87.969 * 86400.0 / model.delta_t) as usize;
+        for _ in 0..steps {
+            model.step();
+            diagnostic.record(&model);
+        }
+        let measured = diagnostic.relativistic_arcseconds_per_century().unwrap();
+        let predicted = diagnostic.tracker.predicted_arcseconds_per_century(&model).unwrap();
+        assert!((predicted - 43.0).abs() < 0.5, "predicted {}\"/century", predicted);
+        assert!((measured - predicted).abs() < 0.05 * predicted, "measured {}\"/century, predicted {}\"/century", measured, predicted);
+    }
+}
@@ -0,0 +1,144 @@
+use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+use crate::structs::Particle;
+use crate::util::*;
+
+// External force fields acting on every particle, on top of the pairwise forces
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum ForceField {
+    UniformGravity {
+        acceleration: [f64; 2],     // m/s^2
+    },
+    LinearDrag {
+        coefficient: f64,           // kg/s, F = -b * v
+    },
+    QuadraticDrag {
+        coefficient: f64,           // kg/m, F = -c * |v| * v
+    },
+    PointMass {
+        position: [f64; 2],         // meters
+        mass: f64,                  // kilograms
+    },
+    Logarithmic {
+        position: [f64; 2],         // meters
+        circular_velocity: f64,     // m/s, asymptotic flat rotation speed
+        core_radius: f64,           // meters
+    },
+    Nfw {
+        position: [f64; 2],         // meters
+        density: f64,               // kg/m^3, characteristic density rho_0
+        scale_radius: f64,          // meters
+    },
+}
+
+impl ForceField {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ForceField::UniformGravity { .. } => "Uniform Gravity",
+            ForceField::LinearDrag { .. } => "Linear Drag",
+            ForceField::QuadraticDrag { .. } => "Quadratic Drag",
+            ForceField::PointMass { .. } => "Point Mass",
+            ForceField::Logarithmic { .. } => "Logarithmic Potential",
+            ForceField::Nfw { .. } => "NFW Halo",
+        }
+    }
+
+    pub(crate) fn force(&self, g_constant: f64, particle: &Particle, delta_t: f64) -> [f64; 2] {
+        match self {
+            ForceField::UniformGravity { acceleration } => [
+                particle.mass * acceleration[0],
+                particle.mass * acceleration[1],
+            ],
+            ForceField::LinearDrag { coefficient } => {
+                let velocity = particle.velocity(delta_t);
+                [-coefficient * velocity[0], -coefficient * velocity[1]]
+            }
+            ForceField::QuadraticDrag { coefficient } => {
+                let velocity = particle.velocity(delta_t);
+                let speed = (velocity[0].powi(2) + velocity[1].powi(2)).sqrt();
+                [-coefficient * speed * velocity[0], -coefficient * speed * velocity[1]]
+            }
+            ForceField::PointMass { position, mass } => {
+                let r = distance(particle.position, *position);
+                if r == 0.0 {
+                    return [0.0, 0.0];
+                }
+                let magnitude = g_constant * mass * particle.mass / r.powi(2);
+                central_force(*position, particle, magnitude)
+            }
+            ForceField::Logarithmic { position, circular_velocity, core_radius } => {
+                let r = distance(particle.position, *position);
+                let magnitude = particle.mass * circular_velocity.powi(2) * r / (core_radius.powi(2) + r.powi(2));
+                central_force(*position, particle, magnitude)
+            }
+            ForceField::Nfw { position, density, scale_radius } => {
+                let r = distance(particle.position, *position);
+                if r == 0.0 {
+                    return [0.0, 0.0];
+                }
+                let x = r / scale_radius;
+                let enclosed_mass = 4.0 * PI * density * scale_radius.powi(3) * ((1.0 + x).ln() - x / (1.0 + x));
+                let magnitude = g_constant * enclosed_mass * particle.mass / r.powi(2);
+                central_force(*position, particle, magnitude)
+            }
+        }
+    }
+
+    // Potential energy of the particle in the field; drag is dissipative and has none
+    pub(crate) fn potential(&self, g_constant: f64, particle: &Particle) -> f64 {
+        match self {
+            ForceField::UniformGravity { acceleration } => {
+                -particle.mass * (acceleration[0] * particle.position[0] + acceleration[1] * particle.position[1])
+            }
+            ForceField::LinearDrag { .. } | ForceField::QuadraticDrag { .. } => 0.0,
+            ForceField::PointMass { position, mass } => {
+                let r = distance(particle.position, *position);
+                if r == 0.0 {
+                    return 0.0;
+                }
+                -g_constant * mass * particle.mass / r
+            }
+            ForceField::Logarithmic { position, circular_velocity, core_radius } => {
+                let r = distance(particle.position, *position);
+                0.5 * particle.mass * circular_velocity.powi(2) * (core_radius.powi(2) + r.powi(2)).ln()
+            }
+            ForceField::Nfw { position, density, scale_radius } => {
+                let r = distance(particle.position, *position);
+                let prefactor = -4.0 * PI * g_constant * density * scale_radius.powi(3);
+                if r == 0.0 {
+                    return particle.mass * prefactor / scale_radius;
+                }
+                particle.mass * prefactor * (1.0 + r / scale_radius).ln() / r
+            }
+        }
+    }
+}
+
+// Checks the values serde cannot, e.g. a negative scale radius
+pub(crate) fn validate_field(field: &ForceField) -> Result<(), String> {
+    let finite = |values: &[f64]| values.iter().all(|value| value.is_finite());
+    let positive = |value: f64| value.is_finite() && value > 0.0;
+    match field {
+        ForceField::UniformGravity { acceleration } if !finite(acceleration) => Err("acceleration must be finite".into()),
+        ForceField::LinearDrag { coefficient } | ForceField::QuadraticDrag { coefficient } if !positive(*coefficient) => {
+            Err("coefficient must be a positive number".into())
+        }
+        ForceField::PointMass { position, mass } if !finite(&[position[0], position[1], *mass]) => Err("position and mass must be finite".into()),
+        ForceField::Logarithmic { position, circular_velocity, core_radius }
+            if !(finite(&[position[0], position[1], *circular_velocity]) && positive(*core_radius)) => {
+            Err("needs a finite position and circular_velocity and a positive core_radius".into())
+        }
+        ForceField::Nfw { position, density, scale_radius }
+            if !(finite(position) && density.is_finite() && *density >= 0.0 && positive(*scale_radius)) => {
+            Err("needs a finite position, a non-negative density and a positive scale_radius".into())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Splits an attractive force of the given magnitude, pointing at the centre, into components
+fn central_force(center: [f64; 2], particle: &Particle, magnitude: f64) -> [f64; 2] {
+    let angle = points_to_horizontal_angle(center, particle.position);
+    vector_to_components(magnitude, angle)
+}
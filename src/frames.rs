@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+// Frame the model is integrated and drawn in; rotating frames turn about the origin
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ReferenceFrame {
+    Inertial,
+    CoRotatingView,     // integrated inertially, drawn rotating with the frame
+    CoRotating,         // integrated in the rotating frame with Coriolis and centrifugal forces
+}
+
+impl ReferenceFrame {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ReferenceFrame::Inertial => "Inertial",
+            ReferenceFrame::CoRotatingView => "Co-rotating (view)",
+            ReferenceFrame::CoRotating => "Co-rotating",
+        }
+    }
+}
+
+pub(crate) fn rotate(vector: [f64; 2], angle: f64) -> [f64; 2] {
+    let (sin, cos) = angle.sin_cos();
+    [
+        vector[0] * cos - vector[1] * sin,
+        vector[0] * sin + vector[1] * cos,
+    ]
+}
+
+// Coriolis plus centrifugal force on a particle moving with the given velocity in a frame rotating at omega
+pub(crate) fn fictitious_force(mass: f64, position: [f64; 2], velocity: [f64; 2], angular_velocity: f64) -> [f64; 2] {
+    let omega_squared = angular_velocity.powi(2);
+    [
+        mass * (omega_squared * position[0] + 2.0 * angular_velocity * velocity[1]),
+        mass * (omega_squared * position[1] - 2.0 * angular_velocity * velocity[0]),
+    ]
+}
@@ -0,0 +1,631 @@
+use std::f64::consts::PI;
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::interactions::{ForceLawKind, Newtonian};
+use crate::orbits::{state_from_true_anomaly, OrbitalElements};
+use crate::structs::{Model, Particle, ParticleKind};
+use crate::units::UnitSystem;
+
+const G_CONSTANT: f64 = 6.674e-11;
+const SOFTENING: f64 = 0.05;        // softening length as a fraction of the scale radius
+const VIRIAL_RATIO: f64 = 0.5;      // kinetic over potential energy in equilibrium
+pub(crate) const POINT_SIZE: f32 = 2.0;    // pixels
+const MAX_GENERATED: usize = 100_000;  // particles one scenario table may ask for
+const MAX_KING_POTENTIAL: f64 = 12.0;  // most concentrated King model whose tidal radius the profile reaches
+
+// Initial state of one generated particle
+#[derive(Clone, Copy)]
+pub(crate) struct Body {
+    pub(crate) position: [f64; 2],  // meters
+    pub(crate) velocity: [f64; 2],  // m/s
+    pub(crate) mass: f64,           // kilograms
+}
+
+// Plummer sphere of the given mass and Plummer radius, drawn in the plane
+pub(crate) fn plummer_sphere(count: usize, total_mass: f64, scale_radius: f64, seed: u64) -> Model {
+    sphere_model(plummer_bodies(count, total_mass, scale_radius, G_CONSTANT, seed), total_mass, scale_radius, "Plummer Sphere")
+}
+
+fn plummer_bodies(count: usize, total_mass: f64, scale_radius: f64, g_constant: f64, seed: u64) -> Vec<Body> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mass = total_mass / count as f64;
+    let mut bodies: Vec<Body> = (0..count)
+        .map(|_| {
+            // Radius from the inverted cumulative mass, cut off at ten scale radii
+            let radius = loop {
+                let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let radius = scale_radius / (u.powf(-2.0 / 3.0) - 1.0).sqrt();
+                if radius < 10.0 * scale_radius {
+                    break radius;
+                }
+            };
+            // Speed as a fraction of the local escape speed, by rejection from q^2 (1 - q^2)^3.5
+            let q = loop {
+                let q: f64 = rng.gen_range(0.0..1.0);
+                if rng.gen_range(0.0..0.1) < q.powi(2) * (1.0 - q.powi(2)).powf(3.5) {
+                    break q;
+                }
+            };
+            let escape_speed = (2.0 * g_constant * total_mass / (radius.powi(2) + scale_radius.powi(2)).sqrt()).sqrt();
+            Body {
+                position: random_direction(&mut rng, radius),
+                velocity: random_direction(&mut rng, q * escape_speed),
+                mass,
+            }
+        })
+        .collect();
+
+    virialise(&mut bodies, SOFTENING * scale_radius, g_constant);
+    bodies
+}
+
+// King model of central potential W0 (concentration) with the given King radius, drawn in the plane
+pub(crate) fn king_model(count: usize, total_mass: f64, scale_radius: f64, central_potential: f64, seed: u64) -> Model {
+    let bodies = king_bodies(count, total_mass, scale_radius, central_potential, G_CONSTANT, seed);
+    sphere_model(bodies, total_mass, scale_radius, "King Model")
+}
+
+fn king_bodies(count: usize, total_mass: f64, scale_radius: f64, central_potential: f64, g_constant: f64, seed: u64) -> Vec<Body> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mass = total_mass / count as f64;
+    let profile = king_profile(central_potential);
+    let enclosed = profile.last().map_or(1.0, |point| point.2);
+
+    let mut bodies: Vec<Body> = (0..count)
+        .map(|_| {
+            // Radius and potential from the tabulated cumulative mass
+            let target = rng.gen_range(0.0..enclosed);
+            let index = profile.partition_point(|point| point.2 < target).min(profile.len() - 1);
+            let (radius, potential, _) = profile[index];
+
+            // Speed in units of the velocity dispersion, by rejection from v^2 (e^(W - v^2 / 2) - 1)
+            let maximum_speed = (2.0 * potential).sqrt();
+            let weight = |speed: f64| speed.powi(2) * ((potential - speed.powi(2) / 2.0).exp() - 1.0);
+            let peak = (1..=50).map(|i| weight(maximum_speed * i as f64 / 50.0)).fold(0.0, f64::max) * 1.1;
+            let speed = loop {
+                let speed = rng.gen_range(0.0..maximum_speed.max(f64::EPSILON));
+                if rng.gen_range(0.0..peak.max(f64::MIN_POSITIVE)) <= weight(speed) {
+                    break speed;
+                }
+            };
+            Body {
+                position: random_direction(&mut rng, radius * scale_radius),
+                velocity: random_direction(&mut rng, speed),
+                mass,
+            }
+        })
+        .collect();
+
+    virialise(&mut bodies, SOFTENING * scale_radius, g_constant);
+    bodies
+}
+
+// Exponential disk of the given scale length in rotation around a central mass
+pub(crate) fn exponential_disk(count: usize, disk_mass: f64, scale_radius: f64, central_mass: f64, seed: u64) -> Model {
+    let bodies = exponential_disk_bodies(count, disk_mass, scale_radius, central_mass, G_CONSTANT, seed);
+    let delta_t = dynamical_time(disk_mass + central_mass, scale_radius) / 200.0;
+    let mut model = generated_model(bodies, delta_t, scale_radius, "Exponential Disk");
+    model.particles[0].name = "Centre".into();
+    model.default_particles[0].name = "Centre".into();
+    model
+}
+
+// The central mass first, then the disk
+fn exponential_disk_bodies(count: usize, disk_mass: f64, scale_radius: f64, central_mass: f64, g_constant: f64, seed: u64) -> Vec<Body> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut bodies = vec![Body { position: [0.0, 0.0], velocity: [0.0, 0.0], mass: central_mass }];
+    bodies.extend(disk_bodies(&mut rng, count, disk_mass, scale_radius, central_mass, false, g_constant));
+    bodies
+}
+
+// Disk particles on circular orbits about a central mass at the origin, with a small random dispersion
+pub(crate) fn disk_bodies(rng: &mut StdRng, count: usize, disk_mass: f64, scale_radius: f64, central_mass: f64, clockwise: bool, g_constant: f64) -> Vec<Body> {
+    let mass = disk_mass / count as f64;
+    let softening = SOFTENING * scale_radius;
+    let spin = if clockwise { -1.0 } else { 1.0 };
+    (0..count)
+        .map(|_| {
+            // The sum of two exponential deviates follows R e^(-R / Rd), cut off at eight scale lengths
+            let radius = loop {
+                let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let v: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let radius = -scale_radius * (u * v).ln();
+                if radius < 8.0 * scale_radius {
+                    break radius;
+                }
+            };
+            let x = radius / scale_radius;
+            let enclosed_mass = central_mass + disk_mass * (1.0 - (1.0 + x) * (-x).exp());
+            let circular_speed = (g_constant * enclosed_mass * radius.powi(2) / (radius.powi(2) + softening.powi(2)).powf(1.5)).sqrt();
+
+            let angle = rng.gen_range(0.0..2.0 * PI);
+            let (sin, cos) = angle.sin_cos();
+            let radial = 0.05 * circular_speed * (rng.gen_range(-1.0..1.0) + rng.gen_range(-1.0..1.0));
+            let tangential = spin * circular_speed * (1.0 + 0.05 * (rng.gen_range(-1.0..1.0) + rng.gen_range(-1.0..1.0)));
+            Body {
+                position: [radius * cos, radius * sin],
+                velocity: [radial * cos - tangential * sin, radial * sin + tangential * cos],
+                mass,
+            }
+        })
+        .collect()
+}
+
+// Model of generated bodies under softened Newtonian gravity, drawn as small points
+pub(crate) fn generated_model(bodies: Vec<Body>, delta_t: f64, scale_radius: f64, name: &str) -> Model {
+    let particles = bodies
+        .iter()
+        .map(|body| Particle::new(body.position, body.velocity, body.mass, delta_t, "/".into(), false))
+        .collect();
+    let mut model = Model::new(particles, delta_t, (150.0 / scale_radius) as f32, G_CONSTANT, false, 1.0, name.into());
+    model.force_law = ForceLawKind::Newtonian(Newtonian { softening: SOFTENING * scale_radius });
+    model.point_size = POINT_SIZE;
+    model.start_at_barycentre();
+    model.units = UnitSystem::henon(&model);
+    model
+}
+
+pub(crate) fn dynamical_time(mass: f64, radius: f64) -> f64 {
+    (radius.powi(3) / (G_CONSTANT * mass)).sqrt()
+}
+
+fn sphere_model(bodies: Vec<Body>, total_mass: f64, scale_radius: f64, name: &str) -> Model {
+    generated_model(bodies, dynamical_time(total_mass, scale_radius) / 200.0, scale_radius, name)
+}
+
+fn random_direction(rng: &mut StdRng, length: f64) -> [f64; 2] {
+    let angle = rng.gen_range(0.0..2.0 * PI);
+    [length * angle.cos(), length * angle.sin()]
+}
+
+// Scales the velocities so the kinetic energy is half the softened potential energy, as the profiles are
+// three dimensional but the particles interact in the plane
+fn virialise(bodies: &mut [Body], softening: f64, g_constant: f64) {
+    let mut potential_energy = 0.0;
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            let separation_squared = (bodies[i].position[0] - bodies[j].position[0]).powi(2)
+                + (bodies[i].position[1] - bodies[j].position[1]).powi(2);
+            potential_energy -= g_constant * bodies[i].mass * bodies[j].mass / (separation_squared + softening.powi(2)).sqrt();
+        }
+    }
+    let kinetic_energy: f64 = bodies
+        .iter()
+        .map(|body| 0.5 * body.mass * (body.velocity[0].powi(2) + body.velocity[1].powi(2)))
+        .sum();
+    if kinetic_energy == 0.0 {
+        return;
+    }
+    let factor = (VIRIAL_RATIO * potential_energy.abs() / kinetic_energy).sqrt();
+    for body in bodies.iter_mut() {
+        body.velocity[0] *= factor;
+        body.velocity[1] *= factor;
+    }
+}
+
+// Dimensionless King profile as (radius in King radii, potential W, enclosed mass) out to the tidal radius
+fn king_profile(central_potential: f64) -> Vec<(f64, f64, f64)> {
+    let density = |potential: f64| {
+        if potential <= 0.0 {
+            return 0.0;
+        }
+        potential.exp() * erf(potential.sqrt()) - (4.0 * potential / PI).sqrt() * (1.0 + 2.0 * potential / 3.0)
+    };
+    let central_density = density(central_potential);
+    // Poisson's equation, W'' = -2 W' / r - 9 rho / rho0, as a first order system in (W, W')
+    let derivative = |radius: f64, state: [f64; 2]| [state[1], -2.0 * state[1] / radius - 9.0 * density(state[0]) / central_density];
+
+    let step = 1e-3;
+    let mut radius = 1e-4;
+    let mut state = [central_potential - 1.5 * radius * radius, -3.0 * radius];
+    let mut enclosed = 0.0;
+    let mut profile = Vec::new();
+    while state[0] > 0.0 && radius < 1e3 {
+        let k1 = derivative(radius, state);
+        let k2 = derivative(radius + step / 2.0, [state[0] + step / 2.0 * k1[0], state[1] + step / 2.0 * k1[1]]);
+        let k3 = derivative(radius + step / 2.0, [state[0] + step / 2.0 * k2[0], state[1] + step / 2.0 * k2[1]]);
+        let k4 = derivative(radius + step, [state[0] + step * k3[0], state[1] + step * k3[1]]);
+        enclosed += density(state[0]) / central_density * radius * radius * step;
+        state[0] += step / 6.0 * (k1[0] + 2.0 * k2[0] + 2.0 * k3[0] + k4[0]);
+        state[1] += step / 6.0 * (k1[1] + 2.0 * k2[1] + 2.0 * k3[1] + k4[1]);
+        radius += step;
+        profile.push((radius, state[0].max(0.0), enclosed));
+    }
+    profile
+}
+
+// Abramowitz and Stegun 7.1.26, accurate to about 1e-7
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    (1.0 - polynomial * (-x * x).exp()).copysign(x)
+}
+
+// A generated cluster, disk or pair of galaxies in a scenario file, about the origin
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum Generator {
+    Plummer {
+        count: usize,
+        total_mass: f64,                    // kilograms
+        scale_radius: f64,                  // meters, the Plummer radius
+        #[serde(default)]
+        seed: u64,
+    },
+    King {
+        count: usize,
+        total_mass: f64,                    // kilograms
+        scale_radius: f64,                  // meters, the King radius
+        central_potential: f64,             // W0, the concentration
+        #[serde(default)]
+        seed: u64,
+    },
+    ExponentialDisk {
+        count: usize,                       // disk particles, after the central mass
+        disk_mass: f64,                     // kilograms
+        scale_radius: f64,                  // meters, the scale length
+        central_mass: f64,                  // kilograms
+        #[serde(default)]
+        seed: u64,
+    },
+    GalaxyMerger(GalaxyEncounter),
+}
+
+impl Generator {
+    pub(crate) fn particles(&self, g_constant: f64, delta_t: f64, do_collisions: bool) -> Vec<Particle> {
+        let (bodies, centres) = match self {
+            Generator::GalaxyMerger(encounter) => encounter_bodies(encounter, g_constant),
+            _ => (self.bodies(g_constant), Vec::new()),
+        };
+        let mut particles: Vec<Particle> = bodies
+            .iter()
+            .map(|body| Particle::new(body.position, body.velocity, body.mass, delta_t, "/".into(), do_collisions))
+            .collect();
+        if let Generator::ExponentialDisk { .. } = self {
+            particles[0].name = "Centre".into();
+        }
+        for (index, name) in centres.into_iter().zip(["Primary", "Secondary"]) {
+            particles[index].name = name.into();
+        }
+        particles
+    }
+
+    fn bodies(&self, g_constant: f64) -> Vec<Body> {
+        match *self {
+            Generator::Plummer { count, total_mass, scale_radius, seed } => plummer_bodies(count, total_mass, scale_radius, g_constant, seed),
+            Generator::King { count, total_mass, scale_radius, central_potential, seed } => {
+                king_bodies(count, total_mass, scale_radius, central_potential, g_constant, seed)
+            }
+            Generator::ExponentialDisk { count, disk_mass, scale_radius, central_mass, seed } => {
+                exponential_disk_bodies(count, disk_mass, scale_radius, central_mass, g_constant, seed)
+            }
+            Generator::GalaxyMerger(ref encounter) => encounter_bodies(encounter, g_constant).0,
+        }
+    }
+
+    // Softening length the generated models use, the force law of scenarios that give none
+    pub(crate) fn softening(&self) -> f64 {
+        match *self {
+            Generator::Plummer { scale_radius, .. } | Generator::King { scale_radius, .. } | Generator::ExponentialDisk { scale_radius, .. } => {
+                SOFTENING * scale_radius
+            }
+            Generator::GalaxyMerger(ref encounter) => SOFTENING * encounter.scale_radius,
+        }
+    }
+}
+
+// Checks the values serde cannot, e.g. a negative scale radius
+pub(crate) fn validate_generator(generator: &Generator) -> Result<(), String> {
+    let (count, scale_radius) = match *generator {
+        Generator::Plummer { count, total_mass, scale_radius, .. } | Generator::King { count, total_mass, scale_radius, .. } => {
+            if !(total_mass.is_finite() && total_mass > 0.0) {
+                return Err("total_mass must be a positive number".into());
+            }
+            (count, scale_radius)
+        }
+        Generator::ExponentialDisk { count, disk_mass, scale_radius, central_mass, .. } => {
+            if !(disk_mass.is_finite() && disk_mass > 0.0) {
+                return Err("disk_mass must be a positive number".into());
+            }
+            if !(central_mass.is_finite() && central_mass >= 0.0) {
+                return Err("central_mass must not be negative".into());
+            }
+            (count, scale_radius)
+        }
+        Generator::GalaxyMerger(ref encounter) => {
+            validate_encounter(encounter)?;
+            (encounter.count, encounter.scale_radius)
+        }
+    };
+    if count == 0 || count > MAX_GENERATED {
+        return Err(format!("count must be between 1 and {}", MAX_GENERATED));
+    }
+    if !(scale_radius.is_finite() && scale_radius > 0.0) {
+        return Err("scale_radius must be a positive number".into());
+    }
+    if let Generator::King { central_potential, .. } = *generator {
+        if !(central_potential > 0.0 && central_potential <= MAX_KING_POTENTIAL) {
+            return Err(format!("central_potential must be above 0 and at most {}", MAX_KING_POTENTIAL));
+        }
+    }
+    Ok(())
+}
+
+fn validate_encounter(encounter: &GalaxyEncounter) -> Result<(), String> {
+    if !(encounter.primary_mass.is_finite() && encounter.primary_mass > 0.0) {
+        return Err("primary_mass must be a positive number".into());
+    }
+    if !(encounter.mass_ratio.is_finite() && encounter.mass_ratio > 0.0) {
+        return Err("mass_ratio must be a positive number".into());
+    }
+    // The secondary's disk gets its share of particles by mass
+    if encounter.count as f64 * (1.0 + encounter.mass_ratio) > MAX_GENERATED as f64 {
+        return Err(format!("the two disks must hold at most {} particles", MAX_GENERATED));
+    }
+    if !(encounter.pericentre.is_finite() && encounter.pericentre > 0.0) {
+        return Err("pericentre must be a positive number".into());
+    }
+    if !(encounter.eccentricity.is_finite() && encounter.eccentricity >= 1.0) {
+        return Err("eccentricity must be at least 1, a parabolic or hyperbolic encounter".into());
+    }
+    if !(encounter.start_distance.is_finite() && encounter.start_distance >= 0.0) {
+        return Err("start_distance must not be negative".into());
+    }
+    Ok(())
+}
+
+// Two disk galaxies meeting on a parabolic or hyperbolic orbit
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct GalaxyEncounter {
+    pub(crate) count: usize,                // disk particles in the primary; the secondary gets its share by mass
+    pub(crate) primary_mass: f64,           // kilograms, a quarter in the disk and the rest in the central mass
+    pub(crate) mass_ratio: f64,             // secondary over primary mass
+    pub(crate) scale_radius: f64,           // meters, primary disk scale length
+    pub(crate) pericentre: f64,             // meters, closest approach of the two centres
+    pub(crate) eccentricity: f64,           // 1 parabolic, above 1 hyperbolic
+    pub(crate) start_distance: f64,         // meters, separation the encounter starts from
+    pub(crate) primary_prograde: bool,      // disk spins the same way as the orbit
+    pub(crate) secondary_prograde: bool,
+    #[serde(default)]
+    pub(crate) seed: u64,
+}
+
+pub(crate) fn galaxy_merger(encounter: GalaxyEncounter) -> Model {
+    let (bodies, centres) = encounter_bodies(&encounter, G_CONSTANT);
+    let distance = encounter.start_distance.max(encounter.pericentre);
+    let delta_t = dynamical_time(encounter.primary_mass, encounter.scale_radius) / 200.0;
+    let mut model = generated_model(bodies, delta_t, encounter.scale_radius, "Galaxy Merger");
+    model.scale_factor = (300.0 / distance) as f32;
+    for (index, name) in centres.into_iter().zip(["Primary", "Secondary"]) {
+        model.particles[index].name = name.into();
+        model.default_particles[index].name = name.into();
+    }
+    model
+}
+
+// Bodies of both galaxies, and the indices of their central masses
+fn encounter_bodies(encounter: &GalaxyEncounter, g_constant: f64) -> (Vec<Body>, Vec<usize>) {
+    let mut rng = StdRng::seed_from_u64(encounter.seed);
+    let primary_mass = encounter.primary_mass;
+    let secondary_mass = primary_mass * encounter.mass_ratio;
+    let total_mass = primary_mass + secondary_mass;
+
+    // Incoming relative orbit of the secondary about the primary, counter-clockwise
+    let semi_latus_rectum = encounter.pericentre * (1.0 + encounter.eccentricity);
+    let distance = encounter.start_distance.max(encounter.pericentre);
+    let true_anomaly = -((semi_latus_rectum / distance - 1.0) / encounter.eccentricity).clamp(-1.0, 1.0).acos();
+    let (position, velocity) = state_from_true_anomaly(g_constant * total_mass, semi_latus_rectum, encounter.eccentricity, 0.0, true_anomaly);
+
+    let galaxies = [
+        (primary_mass, encounter.count, encounter.scale_radius, encounter.primary_prograde, -secondary_mass / total_mass),
+        (
+            secondary_mass,
+            ((encounter.count as f64 * encounter.mass_ratio).round() as usize).max(1),
+            encounter.scale_radius * encounter.mass_ratio.sqrt(),
+            encounter.secondary_prograde,
+            primary_mass / total_mass,
+        ),
+    ];
+    let mut bodies = Vec::new();
+    let mut centres = Vec::new();
+    for (mass, count, scale_radius, prograde, share) in galaxies {
+        let offset = [position[0] * share, position[1] * share];
+        let drift = [velocity[0] * share, velocity[1] * share];
+        let central_mass = 0.75 * mass;
+        centres.push(bodies.len());
+        bodies.push(Body { position: offset, velocity: drift, mass: central_mass });
+        for body in disk_bodies(&mut rng, count, mass - central_mass, scale_radius, central_mass, !prograde, g_constant) {
+            bodies.push(Body {
+                position: [body.position[0] + offset[0], body.position[1] + offset[1]],
+                velocity: [body.velocity[0] + drift[0], body.velocity[1] + drift[1]],
+                mass: body.mass,
+            });
+        }
+    }
+    (bodies, centres)
+}
+
+// Two stars on a Keplerian orbit, starting at periapsis, with planets placed relative to the
+// Holman and Wiegert (1999) stability limits
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BinaryStar {
+    pub(crate) primary_mass: f64,           // kilograms
+    pub(crate) secondary_mass: f64,         // kilograms
+    pub(crate) separation: f64,             // meters, semi-major axis of the binary
+    pub(crate) eccentricity: f64,
+    #[serde(default, rename = "planet")]
+    pub(crate) planets: Vec<BinaryPlanet>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BinaryPlanet {
+    pub(crate) name: String,
+    pub(crate) mass: f64,                   // kilograms
+    pub(crate) host: PlanetHost,
+    pub(crate) critical_ratio: f64,         // circular orbit radius over the critical semi-major axis
+    #[serde(default)]
+    pub(crate) phase: f64,                  // degrees from the binary's periapsis direction
+}
+
+// Circumbinary (P-type) planets are stable outside their critical orbit, S-type planets inside it
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PlanetHost {
+    Circumbinary,
+    Primary,
+    Secondary,
+}
+
+impl BinaryStar {
+    // Critical semi-major axis for a planet around the given host, fitted for eccentricities up to 0.8
+    pub(crate) fn critical_semi_major_axis(&self, host: PlanetHost) -> f64 {
+        let total_mass = self.primary_mass + self.secondary_mass;
+        let e = self.eccentricity;
+        let ratio = match host {
+            PlanetHost::Circumbinary => {
+                let mu = self.primary_mass.min(self.secondary_mass) / total_mass;
+                1.60 + 5.10 * e - 2.22 * e * e + 4.12 * mu - 4.27 * e * mu - 5.09 * mu * mu + 4.61 * e * e * mu * mu
+            }
+            PlanetHost::Primary | PlanetHost::Secondary => {
+                // Mass fraction of the companion perturbing the host
+                let mu = match host {
+                    PlanetHost::Primary => self.secondary_mass,
+                    _ => self.primary_mass,
+                } / total_mass;
+                0.464 - 0.380 * mu - 0.631 * e + 0.586 * mu * e + 0.150 * e * e - 0.198 * mu * e * e
+            }
+        };
+        ratio * self.separation
+    }
+
+    // Stars first, then the planets in order, all moving about the barycentre under the model's G
+    pub(crate) fn particles(&self, g_constant: f64, delta_t: f64, do_collisions: bool) -> Vec<Particle> {
+        let total_mass = self.primary_mass + self.secondary_mass;
+        let elements = OrbitalElements { semi_major_axis: self.separation, eccentricity: self.eccentricity, argument_of_periapsis: 0.0, mean_anomaly: 0.0 };
+        let (position, velocity) = elements.state(g_constant * total_mass);
+        let star = |share: f64, mass: f64, name: &str| Particle::new(
+            [position[0] * share, position[1] * share],
+            [velocity[0] * share, velocity[1] * share],
+            mass,
+            delta_t,
+            name.into(),
+            do_collisions,
+        );
+        let mut particles = vec![
+            star(-self.secondary_mass / total_mass, self.primary_mass, "Primary"),
+            star(self.primary_mass / total_mass, self.secondary_mass, "Secondary"),
+        ];
+
+        let barycentre = Particle::new([0.0, 0.0], [0.0, 0.0], total_mass, delta_t, "/".into(), do_collisions);
+        for planet in &self.planets {
+            let central = match planet.host {
+                PlanetHost::Circumbinary => &barycentre,
+                PlanetHost::Primary => &particles[0],
+                PlanetHost::Secondary => &particles[1],
+            };
+            let elements = OrbitalElements {
+                semi_major_axis: planet.critical_ratio * self.critical_semi_major_axis(planet.host),
+                eccentricity: 0.0,
+                argument_of_periapsis: 0.0,
+                mean_anomaly: planet.phase.to_radians(),
+            };
+            let particle = Particle::from_orbital_elements(central, elements, planet.mass, g_constant, delta_t, planet.name.clone(), do_collisions);
+            particles.push(particle);
+        }
+        particles
+    }
+}
+
+pub(crate) fn binary_star_model(binary: &BinaryStar, name: &str) -> Model {
+    let total_mass = binary.primary_mass + binary.secondary_mass;
+    // Two hundred steps over the fastest orbit, S-type planets or the binary at periapsis
+    let innermost = binary.planets
+        .iter()
+        .map(|planet| planet.critical_ratio * binary.critical_semi_major_axis(planet.host))
+        .fold(binary.separation * (1.0 - binary.eccentricity), f64::min);
+    let delta_t = 2.0 * PI * dynamical_time(total_mass, innermost) / 200.0;
+    let outermost = binary.planets
+        .iter()
+        .map(|planet| planet.critical_ratio * binary.critical_semi_major_axis(planet.host))
+        .fold(binary.separation, f64::max);
+
+    let mut model = Model::new(binary.particles(G_CONSTANT, delta_t, false), delta_t, (300.0 / outermost) as f32, G_CONSTANT, false, 1.0, name.into());
+    model.substeps = 20;
+    model.units = UnitSystem::Astronomical;
+    model
+}
+
+// Many small particles on orbits about a central particle, such as an asteroid belt or a planetary ring
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Belt {
+    pub(crate) primary: usize,              // index of the particle orbited
+    pub(crate) count: usize,
+    pub(crate) inner_radius: f64,           // meters, semi-major axes are drawn between the radii
+    pub(crate) outer_radius: f64,           // meters
+    pub(crate) density_exponent: f64,       // surface density goes as radius to this power
+    pub(crate) eccentricity_dispersion: f64, // Rayleigh scale of the eccentricities, the belt's temperature
+    pub(crate) mass: f64,                   // kilograms, each particle
+    #[serde(default)]
+    pub(crate) test_particles: bool,        // particles feel gravity but exert none, which is much faster
+    #[serde(default)]
+    pub(crate) seed: u64,
+}
+
+// Checks the values serde cannot, e.g. an outer radius inside the inner one
+pub(crate) fn validate_belt(belt: &Belt) -> Result<(), String> {
+    if belt.count == 0 || belt.count > MAX_GENERATED {
+        return Err(format!("count must be between 1 and {}", MAX_GENERATED));
+    }
+    if !(belt.inner_radius.is_finite() && belt.inner_radius > 0.0) {
+        return Err("inner_radius must be a positive number".into());
+    }
+    if !(belt.outer_radius.is_finite() && belt.outer_radius > belt.inner_radius) {
+        return Err("outer_radius must be a finite number above inner_radius".into());
+    }
+    if !belt.density_exponent.is_finite() {
+        return Err("density_exponent must be a finite number".into());
+    }
+    if !(belt.eccentricity_dispersion.is_finite() && belt.eccentricity_dispersion >= 0.0) {
+        return Err("eccentricity_dispersion must not be negative".into());
+    }
+    if !(belt.mass.is_finite() && belt.mass > 0.0) {
+        return Err("mass must be a positive number".into());
+    }
+    Ok(())
+}
+
+// Belt particles about the primary, which must be one of the given particles
+pub(crate) fn belt_particles(particles: &[Particle], belt: &Belt, g_constant: f64, delta_t: f64) -> Vec<Particle> {
+    let central = &particles[belt.primary];
+    let mut rng = StdRng::seed_from_u64(belt.seed);
+    // Inverse of the cumulative distribution of r^(exponent + 1) between the radii
+    let power = belt.density_exponent + 2.0;
+    let (inner, outer) = (belt.inner_radius, belt.outer_radius);
+    (0..belt.count)
+        .map(|_| {
+            let u: f64 = rng.gen_range(0.0..1.0);
+            let semi_major_axis = if power.abs() < 1e-9 {
+                inner * (outer / inner).powf(u)
+            } else {
+                (inner.powf(power) + u * (outer.powf(power) - inner.powf(power))).powf(1.0 / power)
+            };
+            let eccentricity = (belt.eccentricity_dispersion * (-2.0 * rng.gen_range(f64::EPSILON..1.0).ln()).sqrt()).min(0.99);
+            let elements = OrbitalElements {
+                semi_major_axis,
+                eccentricity,
+                argument_of_periapsis: rng.gen_range(0.0..2.0 * PI),
+                mean_anomaly: rng.gen_range(0.0..2.0 * PI),
+            };
+            let mut particle = Particle::from_orbital_elements(central, elements, belt.mass, g_constant, delta_t, "/".into(), false);
+            if belt.test_particles {
+                particle.kind = ParticleKind::Test;
+            }
+            particle
+        })
+        .collect()
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+use crate::checkpoint::{load_checkpoint, save_checkpoint};
+use crate::recorder::{RecordFormat, Recorder};
+use crate::registry::ScenarioRegistry;
+use crate::scenario::{load_scenario, save_scenario};
+use crate::structs::Model;
+use crate::vtk::VtkSeries;
+
+// Options of a run without a window, e.g. `--headless --scenario solar.toml --steps 1000 --save out.toml`
+// or `--headless --resume run.checkpoint --steps 1000 --checkpoint run.checkpoint --checkpoint-every 100`
+// or `--headless --scenario solar.toml --steps 1000 --vtk exports/solar.pvd --vtk-every 10`
+pub(crate) struct HeadlessRun {
+    scenario: Option<String>,               // scenario file, or the name of a model in the registry
+    resume: Option<PathBuf>,                // checkpoint to continue from instead of a scenario
+    steps: u64,                             // time steps to integrate
+    save: Option<PathBuf>,                  // scenario file written after the last step
+    record: Option<PathBuf>,                // trajectory recording, CSV if the path ends in .csv
+    every: u64,                             // steps between recorded frames
+    checkpoint: Option<PathBuf>,            // checkpoint rewritten during the run and after the last step
+    checkpoint_every: Option<u64>,          // steps between checkpoints, only the last step if unset
+    vtk: Option<PathBuf>,                   // ParaView collection of VTK snapshots
+    vtk_every: u64,                         // steps between VTK snapshots
+    seed: Option<u64>,                      // seed of the random numbers, in place of the scenario's
+}
+
+impl HeadlessRun {
+    pub(crate) fn parse(arguments: &[String]) -> Result<Self, String> {
+        let mut scenario = None;
+        let mut steps = 0;
+        let mut save = None;
+        let mut record = None;
+        let mut every = 1;
+        let mut resume = None;
+        let mut checkpoint = None;
+        let mut checkpoint_every = None;
+        let mut vtk = None;
+        let mut vtk_every = 1;
+        let mut seed = None;
+        let mut arguments = arguments.iter();
+        while let Some(argument) = arguments.next() {
+            let mut value = || arguments.next().ok_or(format!("{} needs a value", argument));
+            match argument.as_str() {
+                "--scenario" => scenario = Some(value()?.clone()),
+                "--steps" => steps = value()?.parse().map_err(|_| "--steps must be a whole number".to_string())?,
+                "--save" => save = Some(PathBuf::from(value()?)),
+                "--record" => record = Some(PathBuf::from(value()?)),
+                "--resume" => resume = Some(PathBuf::from(value()?)),
+                "--checkpoint" => checkpoint = Some(PathBuf::from(value()?)),
+                "--checkpoint-every" => {
+                    checkpoint_every = Some(value()?.parse().ok().filter(|&every| every > 0).ok_or("--checkpoint-every must be a positive whole number")?);
+                }
+                "--vtk" => vtk = Some(PathBuf::from(value()?)),
+                "--vtk-every" => vtk_every = value()?.parse().ok().filter(|&every| every > 0).ok_or("--vtk-every must be a positive whole number")?,
+                "--seed" => seed = Some(value()?.parse().map_err(|_| "--seed must be a whole number".to_string())?),
+                "--every" => every = value()?.parse().ok().filter(|&every| every > 0).ok_or("--every must be a positive whole number")?,
+                other => return Err(format!("unknown argument {}", other)),
+            }
+        }
+        if scenario.is_some() == resume.is_some() {
+            return Err("give either --scenario or --resume".into());
+        }
+        if checkpoint_every.is_some() && checkpoint.is_none() {
+            return Err("--checkpoint-every needs --checkpoint".into());
+        }
+        if seed.is_some() && resume.is_some() {
+            return Err("--seed cannot be given with --resume, which continues the checkpoint's random numbers".into());
+        }
+        Ok(HeadlessRun {
+            scenario,
+            resume,
+            steps,
+            save,
+            record,
+            every,
+            checkpoint,
+            checkpoint_every,
+            vtk,
+            vtk_every,
+            seed,
+        })
+    }
+
+    pub(crate) fn run(&self) -> Result<Model, String> {
+        let mut model = match (&self.scenario, &self.resume) {
+            (_, Some(path)) => load_checkpoint(path)?,
+            (Some(scenario), None) => {
+                let mut model = load_model(scenario)?;
+                if let Some(seed) = self.seed {
+                    model.reseed(seed);
+                }
+                model
+            }
+            (None, None) => unreachable!("checked when the arguments were parsed"),
+        };
+        let recording_error = |error: std::io::Error| format!("could not record {}: {}", self.record.as_ref().unwrap().display(), error);
+        let mut recorder = match &self.record {
+            Some(path) => Some(Recorder::create(path, RecordFormat::for_path(path), self.every, &model).map_err(recording_error)?),
+            None => None,
+        };
+        let export_error = |error: std::io::Error| format!("could not export {}: {}", self.vtk.as_ref().unwrap().display(), error);
+        let mut vtk_series = match &self.vtk {
+            Some(path) => {
+                let mut series = VtkSeries::create(path, &model).map_err(export_error)?;
+                series.write_snapshot(&model).map_err(export_error)?;
+                Some(series)
+            }
+            None => None,
+        };
+        for _ in 0..self.steps {
+            model.step();
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(&model).map_err(recording_error)?;
+            }
+            if let Some(series) = vtk_series.as_mut() {
+                series.record(&model, self.vtk_every).map_err(export_error)?;
+            }
+            if let (Some(path), Some(every)) = (&self.checkpoint, self.checkpoint_every) {
+                if model.steps.is_multiple_of(every) {
+                    save_checkpoint(&model, path)?;
+                }
+            }
+        }
+        if let Some(path) = &self.checkpoint {
+            save_checkpoint(&model, path)?;
+        }
+        if let Some(recorder) = recorder {
+            recorder.finish(&model).map_err(recording_error)?;
+        }
+        if let Some(path) = &self.save {
+            save_scenario(&model, path).map_err(|error| error.to_string())?;
+        }
+        Ok(model)
+    }
+}
+
+// A path to a scenario file, otherwise a model listed in the default scenario directory
+fn load_model(scenario: &str) -> Result<Model, String> {
+    let path = Path::new(scenario);
+    if path.is_file() {
+        return load_scenario(path).map_err(|error| error.to_string());
+    }
+    match ScenarioRegistry::new(Path::new("scenarios")).load_named(scenario) {
+        Some(result) => result.map_err(|error| error.to_string()),
+        None => Err(format!("no scenario file or model named {}", scenario)),
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+
+const KILOMETER: f64 = 1.0e3;                  // meters
+const ASTRONOMICAL_UNIT: f64 = 1.495978707e11;  // meters
+const DAY: f64 = 86400.0;                       // seconds
+
+// State of one target from a JPL Horizons VECTORS export, converted to SI units
+pub(crate) struct StateVector {
+    pub(crate) epoch: f64,                      // Julian date, TDB
+    pub(crate) position: [f64; 3],              // meters
+    pub(crate) velocity: [f64; 3],              // m/s
+}
+
+#[derive(Debug)]
+pub(crate) enum HorizonsError {
+    MissingBlock,                               // no $$SOE ... $$EOE data block
+    MissingValue(&'static str),                 // the first record lacks a column
+    InvalidNumber(&'static str, String),        // column and the text that failed to parse
+}
+
+impl fmt::Display for HorizonsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HorizonsError::MissingBlock => write!(f, "no $$SOE/$$EOE data block in the Horizons export"),
+            HorizonsError::MissingValue(column) => write!(f, "the first Horizons record has no {} value", column),
+            HorizonsError::InvalidNumber(column, text) => write!(f, "the Horizons {} value `{}` is not a number", column, text),
+        }
+    }
+}
+
+// Reads the first record of a VECTORS export in either the text or the CSV layout; Z columns are optional
+pub(crate) fn parse_vectors(text: &str) -> Result<StateVector, HorizonsError> {
+    let start = text.find("$$SOE").ok_or(HorizonsError::MissingBlock)?;
+    let end = text[start..].find("$$EOE").ok_or(HorizonsError::MissingBlock)? + start;
+    let header = &text[..start];
+    let block = &text[start + "$$SOE".len()..end];
+
+    let (length_unit, time_unit) = output_units(header);
+    let mut lines = block.lines().map(str::trim).filter(|line| !line.is_empty());
+    let first = lines.next().ok_or(HorizonsError::MissingValue("JDTDB"))?;
+    let values: Vec<(&'static str, f64)> = if first.contains(',') {
+        csv_record(header, first)?
+    } else {
+        // The text layout starts with "JD = A.D. date" and continues on the following lines
+        let mut record = first.to_string();
+        for line in lines.by_ref() {
+            if line.split_whitespace().next().is_some_and(|word| word.parse::<f64>().is_ok()) {
+                break;
+            }
+            record.push(' ');
+            record.push_str(line);
+        }
+        text_record(&record)?
+    };
+
+    let value = |column: &'static str| values.iter().find(|(name, _)| *name == column).map(|(_, value)| *value);
+    let required = |column: &'static str| value(column).ok_or(HorizonsError::MissingValue(column));
+    let speed_unit = length_unit / time_unit;
+    Ok(StateVector {
+        epoch: required("JDTDB")?,
+        position: [
+            required("X")? * length_unit,
+            required("Y")? * length_unit,
+            value("Z").unwrap_or(0.0) * length_unit,
+        ],
+        velocity: [
+            required("VX")? * speed_unit,
+            required("VY")? * speed_unit,
+            value("VZ").unwrap_or(0.0) * speed_unit,
+        ],
+    })
+}
+
+// Meters and seconds per unit of the export's "Output units"; Horizons defaults to km and seconds
+fn output_units(header: &str) -> (f64, f64) {
+    let units = header
+        .lines()
+        .find(|line| line.trim_start().starts_with("Output units"))
+        .unwrap_or("");
+    if units.contains("AU-D") {
+        (ASTRONOMICAL_UNIT, DAY)
+    } else if units.contains("KM-D") {
+        (KILOMETER, DAY)
+    } else {
+        (KILOMETER, 1.0)
+    }
+}
+
+const COLUMNS: [&str; 7] = ["JDTDB", "X", "Y", "Z", "VX", "VY", "VZ"];
+
+fn parse_number(column: &'static str, text: &str) -> Result<f64, HorizonsError> {
+    text.trim().parse().map_err(|_| HorizonsError::InvalidNumber(column, text.trim().to_string()))
+}
+
+// CSV columns are named by the header line just above $$SOE, or take the default VEC_TABLE=2 order
+fn csv_record(header: &str, record: &str) -> Result<Vec<(&'static str, f64)>, HorizonsError> {
+    let fields: Vec<&str> = record.split(',').map(str::trim).collect();
+    let names: Vec<String> = header
+        .lines()
+        .rev()
+        .find(|line| line.contains("JDTDB") && line.contains(','))
+        .map(|line| line.split(',').map(|name| name.trim().to_string()).collect())
+        .unwrap_or_else(|| ["JDTDB", "Calendar Date (TDB)", "X", "Y", "Z", "VX", "VY", "VZ"].map(String::from).to_vec());
+
+    let mut values = Vec::new();
+    for column in COLUMNS {
+        if let Some(index) = names.iter().position(|name| name == column) {
+            if let Some(field) = fields.get(index).filter(|field| !field.is_empty()) {
+                values.push((column, parse_number(column, field)?));
+            }
+        }
+    }
+    Ok(values)
+}
+
+// Text records read "2451545.0 = A.D. ... X =-2.6E+07 Y = 1.3E+08 Z = ... VX= ..."
+fn text_record(record: &str) -> Result<Vec<(&'static str, f64)>, HorizonsError> {
+    let mut values = Vec::new();
+    if let Some(epoch) = record.split_whitespace().next() {
+        values.push(("JDTDB", parse_number("JDTDB", epoch)?));
+    }
+    for column in &COLUMNS[1..] {
+        let mut rest = record;
+        while let Some(found) = rest.find(column) {
+            let before = rest[..found].chars().last();
+            let after = rest[found + column.len()..].trim_start();
+            rest = &rest[found + column.len()..];
+            if before.is_some_and(|c| !c.is_whitespace()) {
+                continue; // "X" inside "VX"
+            }
+            if let Some(number) = after.strip_prefix('=') {
+                let number = number.split_whitespace().next().unwrap_or("");
+                values.push((*column, parse_number(column, number)?));
+                break;
+            }
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f64; 3], expected: [f64; 3]) {
+        for k in 0..3 {
+            assert!((actual[k] - expected[k]).abs() <= 1e-12 * expected[k].abs(), "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn text_export_is_read_in_si_units() {
+        let state = parse_vectors(include_str!("../scenarios/horizons/earth.txt")).unwrap();
+        assert_eq!(state.epoch, 2451545.0);
+        assert_close(state.position, [-2.650444161531104e10, 1.446932274612577e11, 0.0]);
+        assert_close(state.velocity, [-2.979025014993543e4, -5.479468181857718e3, 0.0]);
+    }
+
+    #[test]
+    fn csv_export_in_astronomical_units_is_read_in_si_units() {
+        let export = "\
+Output units    : AU-D
+            JDTDB,            Calendar Date (TDB),                      X,                      Y,                      Z,                     VX,                     VY,                     VZ,
+$$SOE
+2460000.500000000, A.D. 2023-Feb-25 00:00:00.0000,  1.000000000000000E+00, -2.000000000000000E+00,  1.0E-03,  1.000000000000000E-02,  2.000000000000000E-02,  0.0E+00,
+$$EOE
+";
+        let state = parse_vectors(export).unwrap();
+        assert_eq!(state.epoch, 2460000.5);
+        assert_close(state.position, [ASTRONOMICAL_UNIT, -2.0 * ASTRONOMICAL_UNIT, 1.0e-3 * ASTRONOMICAL_UNIT]);
+        assert_close(state.velocity, [0.01 * ASTRONOMICAL_UNIT / DAY, 0.02 * ASTRONOMICAL_UNIT / DAY, 0.0]);
+    }
+
+    #[test]
+    fn export_without_data_block_is_rejected() {
+        assert!(matches!(parse_vectors("Output units : KM-S\n"), Err(HorizonsError::MissingBlock)));
+    }
+}
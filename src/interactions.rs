@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use crate::structs::Particle;
 use crate::util::*;
 
@@ -44,20 +45,24 @@ pub(crate) fn resolve_collision(p1: &Particle, p2: &Particle, restitution: f64,
     }
 
     // Compute impulse scalar
+    let total_inverse_mass = p1.inverse_mass() + p2.inverse_mass();
+    if total_inverse_mass == 0.0 {
+        return [0.0, 0.0, 0.0, 0.0];
+    }
     let impulse_scalar = -(1.0 + restitution) * vel_along_normal
-        / (1.0 / p1.mass + 1.0 / p2.mass);
+        / total_inverse_mass;
 
     // Impulse vector
     let impulse = [impulse_scalar * normal[0], impulse_scalar * normal[1]];
 
     // Compute new velocity adjustments
     let v1_adjustment = [
-        -impulse[0] / p1.mass, // Negative because impulse opposes velocity
-        -impulse[1] / p1.mass,
+        -impulse[0] * p1.inverse_mass(), // Negative because impulse opposes velocity
+        -impulse[1] * p1.inverse_mass(),
     ];
     let v2_adjustment = [
-        impulse[0] / p2.mass, // Positive because impulse adds velocity
-        impulse[1] / p2.mass,
+        impulse[0] * p2.inverse_mass(), // Positive because impulse adds velocity
+        impulse[1] * p2.inverse_mass(),
     ];
 
     // Convert adjustments into position changes for Verlet integration
@@ -67,4 +72,223 @@ pub(crate) fn resolve_collision(p1: &Particle, p2: &Particle, restitution: f64,
         v2_adjustment[0] * delta_t,
         v2_adjustment[1] * delta_t,
     ]
-}
\ No newline at end of file
+}
+// First post-Newtonian correction for a body orbiting a dominant mass (test-particle limit)
+pub(crate) fn resolve_post_newtonian_force(g_constant: f64, speed_of_light: f64, primary: &Particle, particle: &Particle, delta_t: f64) -> [f64; 2] {
+    let relative_position = [
+        particle.position[0] - primary.position[0],
+        particle.position[1] - primary.position[1],
+    ];
+    let primary_velocity = primary.velocity(delta_t);
+    let particle_velocity = particle.velocity(delta_t);
+    let relative_velocity = [
+        particle_velocity[0] - primary_velocity[0],
+        particle_velocity[1] - primary_velocity[1],
+    ];
+
+    let distance = distance(particle.position, primary.position);
+    if distance == 0.0 {
+        return [0.0, 0.0];
+    }
+
+    let mu = g_constant * primary.mass;
+    let speed_squared = relative_velocity[0].powi(2) + relative_velocity[1].powi(2);
+    let radial_velocity = relative_position[0] * relative_velocity[0] + relative_position[1] * relative_velocity[1];
+    let prefactor = particle.mass * mu / (speed_of_light.powi(2) * distance.powi(3));
+
+    [
+        prefactor * ((4.0 * mu / distance - speed_squared) * relative_position[0] + 4.0 * radial_velocity * relative_velocity[0]),
+        prefactor * ((4.0 * mu / distance - speed_squared) * relative_position[1] + 4.0 * radial_velocity * relative_velocity[1]),
+    ]
+}
+
+// Pairwise interaction between two particles; force is the force on particle_i due to particle_j
+pub(crate) trait ForceLaw {
+    fn name(&self) -> &'static str;
+    fn force(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> [f64; 2];
+    fn potential(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> f64;
+}
+
+// Splits a force of the given magnitude, positive towards particle_j, into components
+fn attraction_components(particle_i: &Particle, particle_j: &Particle, magnitude: f64) -> [f64; 2] {
+    let angle = points_to_horizontal_angle(particle_j.position, particle_i.position);
+    vector_to_components(magnitude, angle)
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Newtonian {
+    #[serde(default)]
+    pub(crate) softening: f64,      // meters, Plummer softening length
+}
+
+impl ForceLaw for Newtonian {
+    fn name(&self) -> &'static str {
+        "Newtonian"
+    }
+
+    fn force(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> [f64; 2] {
+        if self.softening == 0.0 {
+            return resolve_gravitation_force(g_constant, particle_i, particle_j);
+        }
+        let r = distance(particle_i.position, particle_j.position);
+        let magnitude = g_constant * particle_i.mass * particle_j.mass * r / (r.powi(2) + self.softening.powi(2)).powf(1.5);
+        attraction_components(particle_i, particle_j, magnitude)
+    }
+
+    fn potential(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> f64 {
+        let r = distance(particle_i.position, particle_j.position);
+        -g_constant * particle_i.mass * particle_j.mass / (r.powi(2) + self.softening.powi(2)).sqrt()
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Coulomb {
+    pub(crate) coulomb_constant: f64,   // N m^2 / C^2
+}
+
+impl ForceLaw for Coulomb {
+    fn name(&self) -> &'static str {
+        "Coulomb"
+    }
+
+    fn force(&self, _g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> [f64; 2] {
+        let r = distance(particle_i.position, particle_j.position);
+        let magnitude = -self.coulomb_constant * particle_i.charge * particle_j.charge / r.powi(2);
+        attraction_components(particle_i, particle_j, magnitude)
+    }
+
+    fn potential(&self, _g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> f64 {
+        let r = distance(particle_i.position, particle_j.position);
+        self.coulomb_constant * particle_i.charge * particle_j.charge / r
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LennardJones {
+    pub(crate) epsilon: f64,        // joules, depth of the potential well
+    pub(crate) sigma: f64,          // meters, distance at which the potential is zero
+}
+
+impl ForceLaw for LennardJones {
+    fn name(&self) -> &'static str {
+        "Lennard-Jones"
+    }
+
+    fn force(&self, _g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> [f64; 2] {
+        let r = distance(particle_i.position, particle_j.position);
+        let ratio_6 = (self.sigma / r).powi(6);
+        let magnitude = 24.0 * self.epsilon / r * (ratio_6 - 2.0 * ratio_6.powi(2));
+        attraction_components(particle_i, particle_j, magnitude)
+    }
+
+    fn potential(&self, _g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> f64 {
+        let r = distance(particle_i.position, particle_j.position);
+        let ratio_6 = (self.sigma / r).powi(6);
+        4.0 * self.epsilon * (ratio_6.powi(2) - ratio_6)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Yukawa {
+    pub(crate) screening_length: f64,   // meters
+}
+
+impl ForceLaw for Yukawa {
+    fn name(&self) -> &'static str {
+        "Yukawa"
+    }
+
+    fn force(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> [f64; 2] {
+        let r = distance(particle_i.position, particle_j.position);
+        let magnitude = g_constant * particle_i.mass * particle_j.mass * (-r / self.screening_length).exp()
+            * (1.0 / r.powi(2) + 1.0 / (self.screening_length * r));
+        attraction_components(particle_i, particle_j, magnitude)
+    }
+
+    fn potential(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> f64 {
+        let r = distance(particle_i.position, particle_j.position);
+        -g_constant * particle_i.mass * particle_j.mass * (-r / self.screening_length).exp() / r
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PowerLaw {
+    pub(crate) exponent: f64,       // n in F = G m1 m2 / r^n
+}
+
+impl ForceLaw for PowerLaw {
+    fn name(&self) -> &'static str {
+        "Power Law"
+    }
+
+    fn force(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> [f64; 2] {
+        let r = distance(particle_i.position, particle_j.position);
+        let magnitude = g_constant * particle_i.mass * particle_j.mass / r.powf(self.exponent);
+        attraction_components(particle_i, particle_j, magnitude)
+    }
+
+    fn potential(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> f64 {
+        let r = distance(particle_i.position, particle_j.position);
+        let strength = g_constant * particle_i.mass * particle_j.mass;
+        if self.exponent == 1.0 {
+            strength * r.ln()
+        } else {
+            -strength / ((self.exponent - 1.0) * r.powf(self.exponent - 1.0))
+        }
+    }
+}
+
+// The force law a model is built with
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum ForceLawKind {
+    Newtonian(Newtonian),
+    Coulomb(Coulomb),
+    LennardJones(LennardJones),
+    Yukawa(Yukawa),
+    PowerLaw(PowerLaw),
+}
+
+// Checks the values serde cannot, e.g. a negative softening length
+pub(crate) fn validate_force_law(force_law: &ForceLawKind) -> Result<(), String> {
+    let positive = |value: f64| value.is_finite() && value > 0.0;
+    match force_law {
+        ForceLawKind::Newtonian(law) if !(law.softening.is_finite() && law.softening >= 0.0) => Err("softening must not be negative".into()),
+        ForceLawKind::Coulomb(law) if !law.coulomb_constant.is_finite() => Err("coulomb_constant must be finite".into()),
+        ForceLawKind::LennardJones(law) if !(law.epsilon.is_finite() && positive(law.sigma)) => Err("needs a finite epsilon and a positive sigma".into()),
+        ForceLawKind::Yukawa(law) if !positive(law.screening_length) => Err("screening_length must be a positive number".into()),
+        ForceLawKind::PowerLaw(law) if !law.exponent.is_finite() => Err("exponent must be finite".into()),
+        _ => Ok(()),
+    }
+}
+
+impl ForceLawKind {
+    fn law(&self) -> &dyn ForceLaw {
+        match self {
+            ForceLawKind::Newtonian(law) => law,
+            ForceLawKind::Coulomb(law) => law,
+            ForceLawKind::LennardJones(law) => law,
+            ForceLawKind::Yukawa(law) => law,
+            ForceLawKind::PowerLaw(law) => law,
+        }
+    }
+}
+
+impl ForceLaw for ForceLawKind {
+    fn name(&self) -> &'static str {
+        self.law().name()
+    }
+
+    fn force(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> [f64; 2] {
+        self.law().force(g_constant, particle_i, particle_j)
+    }
+
+    fn potential(&self, g_constant: f64, particle_i: &Particle, particle_j: &Particle) -> f64 {
+        self.law().potential(g_constant, particle_i, particle_j)
+    }
+}
@@ -6,11 +6,42 @@ mod structs;
 use structs::*;
 mod interactions;
 use interactions::*;
+mod fields;
+mod diagnostics;
+mod spacecraft;
+mod constraints;
+mod motion;
+mod frames;
+mod orbits;
+mod horizons;
+mod generators;
+mod units;
+mod checkpoint;
+use checkpoint::{latest_checkpoint, load_checkpoint, save_checkpoint, CHECKPOINT_EXTENSION};
+mod clock;
+use clock::{calendar_date, current_julian_date, elapsed_text};
+use units::Quantity;
+use frames::ReferenceFrame;
+mod scenario;
+use scenario::{load_scenario, save_scenario};
+mod registry;
+use registry::ScenarioRegistry;
+mod headless;
+mod recorder;
+use recorder::{RecordFormat, Recorder};
+mod vtk;
+use vtk::VtkSeries;
+mod replay;
+use replay::{replay, Recording};
+use headless::HeadlessRun;
+use constraints::Constraint;
+use spacecraft::draw_timeline;
+use diagnostics::*;
 
 use macroquad::prelude::*;
 use std::convert::Into;
+use std::path::{Path, PathBuf};
 use ::rand::Rng;
-use ::rand::thread_rng;
 fn window_conf() -> Conf {
     Conf {
         window_title: "Physics Simulation".to_owned(),
@@ -21,18 +52,97 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
+fn main() {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    if arguments.first().is_some_and(|argument| argument == "--headless") {
+        if let Err(error) = HeadlessRun::parse(&arguments[1..]).and_then(|run| run.run()) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    } // --headless runs a scenario without a window
+    if arguments.first().is_some_and(|argument| argument == "--replay") {
+        let Some(path) = arguments.get(1) else {
+            eprintln!("--replay needs a recording");
+            std::process::exit(1);
+        };
+        match Recording::open(Path::new(path)) {
+            Ok(recording) => macroquad::Window::from_config(window_conf(), replay(recording)),
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    } // --replay plays a recording back
+    match window_arguments(&arguments) {
+        Ok((path, seed)) => macroquad::Window::from_config(window_conf(), run(path, seed)),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+// An optional scenario file or directory, and an optional `--seed N` for the random numbers of the session
+fn window_arguments(arguments: &[String]) -> Result<(Option<PathBuf>, Option<u64>), String> {
+    let mut path = None;
+    let mut seed = None;
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        if argument == "--seed" {
+            let value = arguments.next().ok_or("--seed needs a value")?;
+            seed = Some(value.parse().map_err(|_| "--seed must be a whole number")?);
+        } else {
+            path = Some(PathBuf::from(argument));
+        }
+    }
+    Ok((path, seed))
+}
+
+// A seed given at launch applies to every model, otherwise models that set none use one drawn from the clock
+fn seed_model(model: &mut Model, launch_seed: Option<u64>, session_seed: u64) {
+    match launch_seed {
+        Some(seed) => model.reseed(seed),
+        None if model.seed.is_none() => model.reseed(session_seed),
+        None => {}
+    }
+}
+
+async fn run(start: Option<PathBuf>, launch_seed: Option<u64>) {
+    let session_seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
     let mut selected_model: Model = blank_system();
+    let mut scenario_directory = PathBuf::from("scenarios");
+    if let Some(path) = start {
+        if path.is_dir() {
+            scenario_directory = path;
+        } else {
+            match load_scenario(&path) {
+                Ok(model) => selected_model = model,
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+    } // Optional scenario file to start with, or a directory of scenarios to list
+    seed_model(&mut selected_model, launch_seed, session_seed);
+    let mut registry = ScenarioRegistry::new(&scenario_directory);
+    let mut show_picker = false;
+    let model_keys = [
+        KeyCode::Key1, KeyCode::Key2, KeyCode::Key3,
+        KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+        KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    ];
     let mut scale_factor = selected_model.scale_factor;
     let mut scale_ref = scale_factor;
-    let mut g_constant = selected_model.g_constant;
 
-    let mut elapsed_time = 0.0;
     let mut paused = true;
     let mut change_simulation = false;
+    let mut precession = PrecessionDiagnostic::for_model(&selected_model);
 
     let delta = 1.0;
+    let falling_acceleration = [0.0, 0.01];
+    let drag_coefficient = 0.05;
 
     let mut custom_velocity = [0.0, 0.0];
     let mut change_velocity = false;
@@ -40,22 +150,47 @@ async fn main() {
     let mut custom_mass = 1.0;
     let mut change_mass = false;
 
+    let mut custom_charge = 0.0;
+    let mut change_charge = false;
+
+    let force_laws = [
+        ForceLawKind::Newtonian(Newtonian { softening: 0.0 }),
+        ForceLawKind::Coulomb(Coulomb { coulomb_constant: 0.01 }),
+        ForceLawKind::LennardJones(LennardJones { epsilon: 1.0, sigma: 2.0 }),
+        ForceLawKind::Yukawa(Yukawa { screening_length: 10.0 }),
+        ForceLawKind::PowerLaw(PowerLaw { exponent: 3.0 }),
+    ];
+    let mut force_law_index = 0;
+
+    let mut link_mode = 0; // 0 = off, 1 = spring, 2 = rod
+    let mut link_start: Option<usize> = None;
+    let spring_stiffness = 0.05;
+    let spring_damping = 0.01;
+
+    let mut selected_particle: Option<usize> = None;
+    let mut orbit_primary: Option<usize> = None; // None follows the heaviest other particle
+    let mut status: Option<(String, f64)> = None; // message and the time it was shown
+    let mut recorder: Option<Recorder> = None;
+    let mut vtk_series: Option<VtkSeries> = None;
+
     loop {
         clear_background(BLACK);
 
         // Zoom and Speed
         if is_key_pressed(KeyCode::RightBracket) {
             if !is_key_down(KeyCode::LeftShift) {
-                scale_factor = scale_factor * (11f32 / 10f32).powi(1);
+                scale_factor *= (11f32 / 10f32).powi(1);
             }else{
                 selected_model.change_speed(selected_model.delta_t * (21f64 / 20f64).powi(1));
+                precession = PrecessionDiagnostic::for_model(&selected_model);
             }
         }
         if is_key_pressed(KeyCode::LeftBracket) {
             if !is_key_down(KeyCode::LeftShift) {
-                scale_factor = scale_factor * (11f32 / 10f32).powi(-1);
+                scale_factor *= (11f32 / 10f32).powi(-1);
             } else{
                 selected_model.change_speed(selected_model.delta_t * (21f64 / 20f64).powi(-1));
+                precession = PrecessionDiagnostic::for_model(&selected_model);
             }
         }
 
@@ -75,6 +210,9 @@ async fn main() {
                         custom_mass = 0.1;
                     }
                 }
+                if change_charge {
+                    custom_charge += delta;
+                }
             }else{
                 if change_velocity {
                     custom_velocity[1] += delta / 10.0;
@@ -85,6 +223,9 @@ async fn main() {
                         custom_mass = 0.1;
                     }
                 }
+                if change_charge {
+                    custom_charge += delta / 10.0;
+                }
             }
         }
         if is_key_pressed(KeyCode::Down) {
@@ -98,6 +239,9 @@ async fn main() {
                         custom_mass = -0.1;
                     }
                 }
+                if change_charge {
+                    custom_charge -= delta;
+                }
             }else{
                 if change_velocity {
                     custom_velocity[1] -= delta / 10.0;
@@ -108,6 +252,9 @@ async fn main() {
                         custom_mass = -0.1;
                     }
                 }
+                if change_charge {
+                    custom_charge -= delta / 10.0;
+                }
             }
         }
         if is_key_pressed(KeyCode::Left) {
@@ -134,27 +281,93 @@ async fn main() {
         }
 
         if is_mouse_button_pressed(MouseButton::Right) {
-            selected_model.particles.push(
-                Particle::new(
-                    world_mouse_pos,
-                    custom_velocity,
-                    custom_mass,
-                    selected_model.delta_t,
-                    "/".into(),
-                    selected_model.do_collisions,
-                )
+            let mut custom_particle = Particle::new(
+                selected_model.world_position(world_mouse_pos),
+                custom_velocity,
+                custom_mass,
+                selected_model.delta_t,
+                "/".into(),
+                selected_model.do_collisions,
             );
+            custom_particle.charge = custom_charge;
+            selected_model.particles.push(custom_particle);
+            precession = PrecessionDiagnostic::for_model(&selected_model);
         } // Custom Particle
-        if is_mouse_button_pressed(MouseButton::Left) {
-            let mut rng = thread_rng();
-            let mut random_mass: f64 = rng.gen_range(0.0..10.0);
+        if is_mouse_button_pressed(MouseButton::Middle) {
+            selected_particle = selected_model.particle_at(world_mouse_pos, 10.0 / scale_factor as f64);
+        } // Select particle under the cursor
+        if is_key_pressed(KeyCode::Tab) && !selected_model.particles.is_empty() {
+            selected_particle = Some(selected_particle.map_or(0, |i| (i + 1) % selected_model.particles.len()));
+        } // Cycle selected particle
+
+        if is_key_pressed(KeyCode::P) {
+            if let Some(i) = selected_particle {
+                if is_key_down(KeyCode::LeftShift) {
+                    selected_model.toggle_kinematic(i);
+                } else {
+                    selected_model.toggle_pinned(i);
+                }
+                precession = PrecessionDiagnostic::for_model(&selected_model);
+            }
+        } // Toggle pinned | Shift+P -> Toggle kinematic circular path
+
+        if is_key_pressed(KeyCode::N) {
+            if let Some(i) = selected_particle {
+                let start = orbit_primary.map_or(0, |primary| primary + 1);
+                orbit_primary = (start..selected_model.particles.len()).find(|&primary| primary != i);
+            }
+        } // Cycle the primary the selected particle's orbit is measured about, ending back on automatic
+
+        if is_key_pressed(KeyCode::O) {
+            let angular_velocity = selected_model.binary_angular_velocity();
+            let next_frame = match selected_model.frame {
+                ReferenceFrame::Inertial => ReferenceFrame::CoRotatingView,
+                ReferenceFrame::CoRotatingView => ReferenceFrame::CoRotating,
+                ReferenceFrame::CoRotating => ReferenceFrame::Inertial,
+            };
+            selected_model.set_frame(next_frame, angular_velocity);
+            precession = PrecessionDiagnostic::for_model(&selected_model);
+        } // Cycle reference frame: co-rotating view -> co-rotating integration -> inertial
+        if is_key_pressed(KeyCode::B) {
+            selected_model.recentre_to_barycentre();
+            precession = PrecessionDiagnostic::for_model(&selected_model);
+        } // Recentre on the barycentre
+
+        if is_key_pressed(KeyCode::L) {
+            link_mode = (link_mode + 1) % 3;
+            link_start = None;
+        } // Cycle link mode: spring -> rod -> off
+
+        if is_mouse_button_pressed(MouseButton::Left) && link_mode != 0 {
+            let picked = selected_model.particle_at(world_mouse_pos, 10.0 / scale_factor as f64);
+            match (link_start, picked) {
+                (Some(a), Some(b)) if a != b => {
+                    let length = distance(selected_model.particles[a].position, selected_model.particles[b].position);
+                    if link_mode == 1 {
+                        selected_model.constraints.push(Constraint::Spring {
+                            a,
+                            b,
+                            rest_length: length,
+                            stiffness: spring_stiffness,
+                            damping: spring_damping,
+                        });
+                    } else {
+                        selected_model.constraints.push(Constraint::Rod { a, b, length });
+                    }
+                    precession = PrecessionDiagnostic::for_model(&selected_model);
+                    link_start = None;
+                }
+                _ => link_start = picked,
+            }
+        } else if is_mouse_button_pressed(MouseButton::Left) {
+            let mut random_mass: f64 = selected_model.rng.gen_range(0.0..10.0);
 
             random_mass = random_mass.trunc();
             if random_mass == 0.0 { random_mass = 1.0; }
 
             selected_model.particles.push(
                 Particle::new(
-                    world_mouse_pos,
+                    selected_model.world_position(world_mouse_pos),
                     [0.0, 0.0],
                     random_mass,
                     selected_model.delta_t,
@@ -162,153 +375,203 @@ async fn main() {
                     selected_model.do_collisions,
                 )
             );
+            precession = PrecessionDiagnostic::for_model(&selected_model);
         } // Random Particle
 
         if is_key_pressed(KeyCode::Space) {
             paused = !paused;
         } // Toggle Pause
 
-        if is_key_pressed(KeyCode::Key1){ // Select model 1
-            if !is_key_down(KeyCode::LeftShift) {
-                if is_key_down(KeyCode::LeftControl) {
-                    selected_model = blank_system();
-                    change_simulation = true;
-                }
-            }else{
-                change_mass = !change_mass;
+        if is_key_pressed(KeyCode::F) {
+            selected_model.toggle_uniform_gravity(falling_acceleration);
+            precession = PrecessionDiagnostic::for_model(&selected_model);
+        } // Toggle falling mode
+        if is_key_pressed(KeyCode::D) {
+            selected_model.cycle_drag(drag_coefficient);
+            precession = PrecessionDiagnostic::for_model(&selected_model);
+        } // Cycle drag: linear -> quadratic -> off
+
+        if is_key_pressed(KeyCode::Key1) && is_key_down(KeyCode::LeftShift) {
+            change_mass = !change_mass;
+            change_velocity = false;
+            change_charge = false;
+        } // Shift+1 -> Select Mass
+        if is_key_pressed(KeyCode::Key2) && is_key_down(KeyCode::LeftShift) {
+            change_velocity = !change_velocity;
+            change_mass = false;
+            change_charge = false;
+        } // Shift+2 -> Select Velocity
+        if is_key_pressed(KeyCode::Key3) {
+            if is_key_down(KeyCode::LeftShift) {
+                change_charge = !change_charge;
                 change_velocity = false;
+                change_mass = false;
+            } else {
+                change_velocity = false;
+                change_mass = false;
+                change_charge = false;
             }
-        } // Shift+1 -> Select Mass | Left Ctrl+1 -> Select Model 1
-        if is_key_pressed(KeyCode::Key2) { // Select model 2
-            if !is_key_down(KeyCode::LeftShift) {
-                if is_key_down(KeyCode::LeftControl) {
-                    selected_model = solar_system();
-                    change_simulation = true;
+        } // Shift+3 -> Select Charge
+
+        if is_key_down(KeyCode::LeftControl) {
+            for (slot, key) in model_keys.iter().enumerate() {
+                if is_key_pressed(*key) {
+                    match registry.load_slot(slot + 1) {
+                        Some(Ok(model)) => {
+                            selected_model = model;
+                            seed_model(&mut selected_model, launch_seed, session_seed);
+                            change_simulation = true;
+                        }
+                        Some(Err(error)) => eprintln!("{}", error),
+                        None => {}
+                    }
                 }
-            }else{
-                change_velocity = !change_velocity;
-                change_mass = false;
             }
-        } // Shift+1 -> Select Velocity | Left Ctrl+2 -> Select Model 2
-        if is_key_pressed(KeyCode::Key3) { // Select model 3
-            if is_key_down(KeyCode::LeftControl) {
-                selected_model = earth_moon_system();
-                change_simulation = true;
+        } // Left Ctrl+1..9 -> Select a model from the current registry page
+        if is_key_pressed(KeyCode::PageDown) {
+            registry.change_page(1);
+        }
+        if is_key_pressed(KeyCode::PageUp) {
+            registry.change_page(-1);
+        } // Page through the registry
+        if is_key_pressed(KeyCode::M) {
+            show_picker = !show_picker;
+        } // Toggle model picker
+        if is_key_pressed(KeyCode::F5) {
+            registry.scan();
+        } // Rescan the scenario directory
+
+        if is_key_pressed(KeyCode::S) {
+            let path = next_free_path(Path::new("saves"), &selected_model.name, "toml");
+            let message = match save_scenario(&selected_model, &path) {
+                Ok(()) => format!("Saved to {}", path.display()),
+                Err(error) => error.to_string(),
+            };
+            status = Some((message, get_time()));
+        } // Save the current model as a scenario file
+
+        if is_key_pressed(KeyCode::C) {
+            let directory = Path::new("checkpoints");
+            let message = if is_key_down(KeyCode::LeftShift) {
+                match latest_checkpoint(directory, &selected_model.name).ok_or("No checkpoint of this model".to_string()).and_then(|path| load_checkpoint(&path)) {
+                    Ok(model) => {
+                        selected_model = model;
+                        change_simulation = true;
+                        "Resumed from the latest checkpoint".to_string()
+                    }
+                    Err(error) => error,
+                }
+            } else {
+                let path = next_free_path(directory, &selected_model.name, CHECKPOINT_EXTENSION);
+                match save_checkpoint(&selected_model, &path) {
+                    Ok(()) => format!("Checkpoint written to {}", path.display()),
+                    Err(error) => error,
+                }
+            };
+            status = Some((message, get_time()));
+        } // Write a checkpoint | Shift+C -> Resume from the latest checkpoint of this model
+
+        if is_key_pressed(KeyCode::V) {
+            let message = match recorder.take() {
+                Some(active) => match active.finish(&selected_model) {
+                    Ok(path) => format!("Recorded to {}", path.display()),
+                    Err(error) => format!("Recording failed: {}", error),
+                },
+                None => {
+                    let format = if is_key_down(KeyCode::LeftShift) { RecordFormat::Binary } else { RecordFormat::Csv };
+                    let path = next_free_path(Path::new("recordings"), &selected_model.name, format.extension());
+                    match Recorder::create(&path, format, selected_model.substeps as u64, &selected_model) {
+                        Ok(started) => {
+                            recorder = Some(started);
+                            format!("Recording to {}", path.display())
+                        }
+                        Err(error) => format!("Recording failed: {}", error),
+                    }
+                }
+            };
+            status = Some((message, get_time()));
+        } // Start or stop recording trajectories to CSV | Shift+V -> binary
+
+        if is_key_pressed(KeyCode::X) {
+            let message = if is_key_down(KeyCode::LeftShift) {
+                match vtk_series.take() {
+                    Some(series) => format!("Exported {} snapshots to {}", series.len(), series.path.display()),
+                    None => "No VTK export in progress".to_string(),
+                }
+            } else {
+                let series = match vtk_series.take() {
+                    Some(series) => Ok(series),
+                    None => VtkSeries::create(&next_free_path(Path::new("exports"), &selected_model.name, "pvd"), &selected_model),
+                };
+                match series.and_then(|mut series| series.write_snapshot(&selected_model).map(|()| series)) {
+                    Ok(series) => {
+                        let message = format!("Snapshot {} exported to {}", series.len(), series.path.display());
+                        vtk_series = Some(series);
+                        message
+                    }
+                    Err(error) => format!("VTK export failed: {}", error),
+                }
+            };
+            status = Some((message, get_time()));
+        } // Export a VTK snapshot to the current series | Shift+X -> End the series
+
+        if is_key_pressed(KeyCode::I) {
+            force_law_index = (force_law_index + 1) % force_laws.len();
+            selected_model.force_law = force_laws[force_law_index].clone();
+            precession = PrecessionDiagnostic::for_model(&selected_model);
+        } // Cycle pairwise force law
+
+        if is_key_pressed(KeyCode::G) {
+            selected_model.relativistic = !selected_model.relativistic;
+            precession = PrecessionDiagnostic::for_model(&selected_model);
+        } // Toggle post-Newtonian correction
+
+        if change_simulation || is_key_pressed(KeyCode::R) {
+            if let Some(active) = recorder.take() {
+                let message = match active.finish(&selected_model) {
+                    Ok(path) => format!("Recorded to {}", path.display()),
+                    Err(error) => format!("Recording failed: {}", error),
+                };
+                status = Some((message, get_time()));
             }
-            change_velocity = false;
-            change_mass = false;
-        } // Left Ctrl+2 -> Select Model 3
+            vtk_series = None;
+        } // A recording or VTK series covers one run of one model
 
         if change_simulation {
-            g_constant = selected_model.g_constant;
             scale_factor = selected_model.scale_factor;
             selected_model.particles = selected_model.particles.clone();
             scale_ref = scale_factor;
-            elapsed_time = 0.0;
             paused = true;
             change_simulation = false;
+            force_law_index = 0;
+            link_start = None;
+            selected_particle = None;
+            orbit_primary = None;
+            precession = PrecessionDiagnostic::for_model(&selected_model);
         } // Update simulation parameters
 
         if is_key_pressed(KeyCode::R) {
             selected_model.reset();
-            elapsed_time = 0.0;
+            link_start = None;
+            selected_particle = None;
+            orbit_primary = None;
             paused = true;
+            precession = PrecessionDiagnostic::for_model(&selected_model);
         } // Reset simulation
 
         if !paused {
-            // Phase 1: Resolve overlaps
-            for i in 0..selected_model.particles.len() {
-                for j in (i + 1)..selected_model.particles.len() {
-                    let distance = distance(
-                        selected_model.particles[i].position,
-                        selected_model.particles[j].position,
-                    );
-
-                    if distance < selected_model.particles[i].radius + selected_model.particles[j].radius {
-                        let overlap = selected_model.particles[i].radius
-                            + selected_model.particles[j].radius
-                            - distance;
-
-                        if overlap > 0.0 {
-                            let total_mass = selected_model.particles[i].mass + selected_model.particles[j].mass;
-                            let mass_ratio_i = selected_model.particles[j].mass / total_mass;
-                            let mass_ratio_j = selected_model.particles[i].mass / total_mass;
-
-                            // Calculate separation vector
-                            let mut separation_vector = [
-                                selected_model.particles[j].position[0] - selected_model.particles[i].position[0],
-                                selected_model.particles[j].position[1] - selected_model.particles[i].position[1],
-                            ];
-
-                            let separation_magnitude = (separation_vector[0].powi(2) + separation_vector[1].powi(2)).sqrt();
-                            separation_vector[0] /= separation_magnitude;
-                            separation_vector[1] /= separation_magnitude;
-
-                            // Apply separation proportional to mass
-                            selected_model.particles[i].position[0] -= separation_vector[0] * overlap * mass_ratio_i;
-                            selected_model.particles[i].position[1] -= separation_vector[1] * overlap * mass_ratio_i;
-                            selected_model.particles[j].position[0] += separation_vector[0] * overlap * mass_ratio_j;
-                            selected_model.particles[j].position[1] += separation_vector[1] * overlap * mass_ratio_j;
-                        }
-
-                    }
+            for _ in 0..selected_model.substeps {
+                selected_model.step();
+                if let Some(diagnostic) = precession.as_mut() {
+                    diagnostic.record(&selected_model);
                 }
-            }
-
-            // Phase 2: Apply gravitational forces and resolve collisions
-            for i in 0..selected_model.particles.len() {
-                selected_model.particles[i].force = [0.0, 0.0];
-
-                for j in 0..selected_model.particles.len() {
-                    if i != j {
-                        let distance = distance(
-                            selected_model.particles[i].position,
-                            selected_model.particles[j].position,
-                        );
-
-                        let mut g_components = resolve_gravitation_force(
-                            g_constant,
-                            &selected_model.particles[i],
-                            &selected_model.particles[j],
-                        );
-
-                        // Prevent gravitational forces for overlapping particles
-                        if distance <= selected_model.particles[i].radius + selected_model.particles[j].radius {
-                            g_components = [0.0, 0.0];
-                        }
-
-                        selected_model.particles[i].force[0] += g_components[0];
-                        selected_model.particles[i].force[1] += g_components[1];
-
-                        // Handle collisions
-                        if check_collision(
-                            &selected_model.particles[i],
-                            &selected_model.particles[j],
-                        ) {
-                            let impulse = resolve_collision(
-                                &selected_model.particles[i],
-                                &selected_model.particles[j],
-                                selected_model.restitution,
-                                selected_model.delta_t,
-                            );
-                            selected_model.particles[i].previous_position[0] -= impulse[0];
-                            selected_model.particles[i].previous_position[1] -= impulse[1];
-                            selected_model.particles[j].previous_position[0] -= impulse[2];
-                            selected_model.particles[j].previous_position[1] -= impulse[3];
-                        }
-                    }
+                if let Some(error) = recorder.as_mut().and_then(|active| active.record(&selected_model).err()) {
+                    status = Some((format!("Recording failed: {}", error), get_time()));
+                    recorder = None;
                 }
             }
         }
 
-        // Update particles
-        if !paused {
-            for i in 0..selected_model.particles.len() {
-                selected_model.particles[i].update(selected_model.delta_t);
-            }
-            elapsed_time += selected_model.delta_t;
-      }
-
 
         draw_text(
             &format!("Selected Model: {:#}", selected_model.name),
@@ -318,11 +581,7 @@ async fn main() {
             YELLOW,
         );
 
-        if paused {
-            draw_text(&format!("Frames elapsed: {}", elapsed_time.trunc()), 20.0, 50.0, 16.0, RED);
-        } else {
-            draw_text(&format!("Frames elapsed: {}", elapsed_time.trunc()), 20.0, 50.0, 16.0, YELLOW);
-        }
+        draw_text(&format!("Elapsed: {} ({} steps)", elapsed_text(&selected_model), selected_model.steps), 20.0, 50.0, 16.0, if paused { RED } else { YELLOW });
         draw_text(
             &format!("Number of particles: {}", selected_model.particles.len()),
             20.0,
@@ -349,25 +608,114 @@ async fn main() {
             draw_text(&format!("Velocity: {:?}", [round_to_place(custom_velocity[0],2),round_to_place(custom_velocity[1],2)]), 20.0, 170.0, 16.0, RED, );
         }
 
+        if !change_charge {
+            draw_text(&format!("Charge: {:?}", round_to_place(custom_charge,2)), 20.0, 200.0, 16.0, YELLOW, );
+        } else {
+            draw_text(&format!("Charge: {:?}", round_to_place(custom_charge,2)), 20.0, 200.0, 16.0, RED, );
+        }
+
+        if let Some(diagnostic) = precession.as_ref() {
+            let body = &selected_model.particles[diagnostic.tracker.body].name;
+            let measured = diagnostic.tracker.arcseconds_per_century().unwrap_or(0.0);
+            if let Some(excess) = diagnostic.relativistic_arcseconds_per_century() {
+                draw_text(&format!("Perihelion Precession ({}): {:.2}\"/century, relativistic part {:.2}\"/century (1PN predicts {:.2}\"/century)",
+                                   body,
+                                   measured,
+                                   excess,
+                                   diagnostic.tracker.predicted_arcseconds_per_century(&selected_model).unwrap_or(0.0)),
+                          20.0,
+                          screen_height() - 290.0,
+                          16.0,
+                          RED
+                );
+            } else {
+                draw_text(&format!("Perihelion Precession ({}): {:.2}\"/century", body, measured), 20.0, screen_height() - 290.0, 16.0, RED);
+            }
+        }
+        draw_text(&format!("Relativistic Correction: {}", if selected_model.relativistic { "On" } else { "Off" }), 20.0, screen_height() - 250.0, 16.0, RED);
+        let field_names: Vec<&str> = selected_model.force_fields.iter().map(|field| field.name()).collect();
+        draw_text(&format!("Force Fields: {}", if field_names.is_empty() { "None".to_string() } else { field_names.join(", ") }), 20.0, screen_height() - 230.0, 16.0, RED);
+        draw_text(&format!("Link Mode: {}", ["Off", "Spring", "Rod"][link_mode]), 20.0, 230.0, 16.0, if link_mode == 0 { YELLOW } else { RED });
+        draw_text(&format!("Frame: {}", selected_model.frame.name()), 20.0, screen_height() - 310.0, 16.0, RED);
+        if let Some(active) = &recorder {
+            draw_text(&format!("Recording {} ({} frames)", active.path.display(), active.frames), 20.0, screen_height() - 370.0, 16.0, RED);
+        }
+        if let Some(series) = &vtk_series {
+            draw_text(&format!("VTK Series {} ({} snapshots)", series.path.display(), series.len()), 20.0, screen_height() - 390.0, 16.0, RED);
+        }
+        draw_text(&format!("Units: {}", selected_model.units.name()), 20.0, screen_height() - 350.0, 16.0, RED);
+        if let Some(date) = current_julian_date(&selected_model) {
+            draw_text(&format!("Date: {} TDB (JD {:.4})", calendar_date(date), date), 20.0, screen_height() - 330.0, 16.0, RED);
+        }
+        draw_text(&format!("Force Law: {}", selected_model.force_law.name()), 20.0, screen_height() - 270.0, 16.0, RED);
         draw_text(&format!("Restitution Value: {:.2}", selected_model.restitution), 20.0, screen_height() - 210.0, 16.0, RED);
-        draw_text(&format!("G Constant: {}", selected_model.g_constant), 20.0, screen_height() - 190.0, 16.0, RED);
+        draw_text(&format!("G Constant: {}", selected_model.units.gravitational_constant(selected_model.g_constant)), 20.0, screen_height() - 190.0, 16.0, RED);
 
         draw_text(&format!("Zoom: {}%", round_to_place((scale_factor/ scale_ref * 100.0).into(),2)), 20.0, screen_height() - 160.0, 16.0, RED);
-        draw_text(&format!("Time Step: {:.3}", selected_model.delta_t), 20.0, screen_height() - 130.0, 16.0, RED);
-        draw_text(&format!("({}% of default: {:.3})",
+        draw_text(&format!("Time Step: {} x {} per frame", selected_model.units.format(selected_model.delta_t, Quantity::Time), selected_model.substeps), 20.0, screen_height() - 130.0, 16.0, RED);
+        draw_text(&format!("({}% of default: {})",
                            round_to_place((selected_model.delta_t/selected_model.default_delta_t)*100.0,2),
-                           selected_model.default_delta_t),
+                           selected_model.units.format(selected_model.default_delta_t, Quantity::Time)),
                   20.0,
                   screen_height() - 100.0,
                   16.0,
                   RED
         );
 
-        draw_text(&format!("Mouse X: {}", world_mouse_pos[0]), 20.0, screen_height() - 70.0, 16.0, RED);
-        draw_text(&format!("Mouse Y: {}", world_mouse_pos[1]), 20.0, screen_height() - 40.0, 16.0, RED);
+        draw_text(&format!("Mouse X: {}", selected_model.units.format(world_mouse_pos[0], Quantity::Length)), 20.0, screen_height() - 70.0, 16.0, RED);
+        draw_text(&format!("Mouse Y: {}", selected_model.units.format(world_mouse_pos[1], Quantity::Length)), 20.0, screen_height() - 40.0, 16.0, RED);
+        if let Some((message, shown)) = &status {
+            if get_time() - shown < 3.0 {
+                draw_text(message, 20.0, screen_height() - 10.0, 16.0, YELLOW);
+            }
+        }
 
 
+        if let Some(a) = link_start {
+            let (start_x, start_y) = world_to_screen(selected_model.display_position(a), scale_factor);
+            let (mouse_x, mouse_y) = mouse_position();
+            draw_line(start_x, start_y, mouse_x, mouse_y, 1.0, RED);
+        }
+
         selected_model.draw(scale_factor);
+        if let Some(i) = selected_particle {
+            let particle = &selected_model.particles[i];
+            let (screen_x, screen_y) = world_to_screen(selected_model.display_position(i), scale_factor);
+            let radius = if selected_model.do_collisions { (particle.radius * scale_factor as f64) as f32 } else { selected_model.point_size };
+            draw_circle_lines(screen_x, screen_y, radius + 4.0, 1.5, YELLOW);
+            draw_text(&format!("Selected: {} ({})", if particle.name == "/" { format!("#{}", i) } else { particle.name.clone() }, particle.motion.name()), 20.0, 260.0, 16.0, YELLOW);
+
+            let primary = orbit_primary.filter(|&primary| primary != i).or(selected_model.primary_of(i));
+            if let Some((primary, orbit)) = primary.and_then(|primary| selected_model.orbit_about(i, primary).map(|orbit| (primary, orbit))) {
+                let primary_name = &selected_model.particles[primary].name;
+                let elements = orbit.elements;
+                let units = selected_model.units;
+                let lines = [
+                    format!("Orbit about {}{}{}",
+                            if primary_name == "/" { format!("#{}", primary) } else { primary_name.clone() },
+                            if orbit_primary.is_none() { " (auto)" } else { "" },
+                            if orbit.retrograde { ", retrograde" } else { "" }),
+                    format!("Semi-major axis: {}", units.format(elements.semi_major_axis, Quantity::Length)),
+                    format!("Eccentricity: {:.5}", elements.eccentricity),
+                    match orbit.period {
+                        Some(period) => format!("Period: {}", units.format(period, Quantity::Time)),
+                        None => "Period: unbound".to_string(),
+                    },
+                    match orbit.apoapsis {
+                        Some(apoapsis) => format!("Periapsis / Apoapsis: {} / {}", units.format(orbit.periapsis, Quantity::Length), units.format(apoapsis, Quantity::Length)),
+                        None => format!("Periapsis: {}", units.format(orbit.periapsis, Quantity::Length)),
+                    },
+                    format!("True Anomaly: {:.2} deg", orbit.true_anomaly.to_degrees()),
+                ];
+                for (line, text) in lines.iter().enumerate() {
+                    draw_text(text, 20.0, 290.0 + line as f32 * 20.0, 16.0, YELLOW);
+                }
+            }
+        }
+        draw_timeline(&selected_model);
+        if show_picker {
+            registry.draw_picker(&selected_model.name);
+        }
         next_frame().await;
     }
 }
@@ -1,5 +1,45 @@
 use crate::structs::*;
+use crate::scenario::parse_scenario;
+use crate::generators::{belt_particles, binary_star_model, Belt, BinaryPlanet, BinaryStar, PlanetHost};
+use crate::orbits::OrbitalElements;
+use crate::units::UnitSystem;
 static G_CONSTANT: f64 = 6.674e-11;
+static ASTRONOMICAL_UNIT: f64 = 1.495978707e11; // meters
+static SOLAR_MASS: f64 = 1.989e30; // kilograms
+static J2000: f64 = 2451545.0; // Julian date (TDB) of the J2000 elements
+pub(crate) static LUNAR_TRANSFER_SCENARIO: &str = include_str!("../scenarios/lunar_transfer.toml");
+
+// Elements from a semi-major axis in meters, eccentricity, and mean longitude and longitude of periapsis in degrees
+fn elements(semi_major_axis: f64, eccentricity: f64, mean_longitude: f64, periapsis_longitude: f64) -> OrbitalElements {
+    OrbitalElements {
+        semi_major_axis,
+        eccentricity,
+        argument_of_periapsis: periapsis_longitude.to_radians(),
+        mean_anomaly: (mean_longitude - periapsis_longitude).to_radians(),
+    }
+}
+
+fn orbiting(central: &Particle, name: &str, mass: f64, elements: OrbitalElements, delta_t: f64) -> Particle {
+    Particle::from_orbital_elements(central, elements, mass, G_CONSTANT, delta_t, String::from(name), false)
+}
+
+// The planets and Pluto about the Sun from J2000 mean elements (Standish): semi-major axis in AU, eccentricity, mean longitude and longitude of perihelion
+fn planets(sun: &Particle, delta_t: f64) -> Vec<Particle> {
+    let planet = |name: &str, mass: f64, semi_major_axis: f64, eccentricity: f64, mean_longitude: f64, perihelion_longitude: f64| {
+        orbiting(sun, name, mass, elements(semi_major_axis * ASTRONOMICAL_UNIT, eccentricity, mean_longitude, perihelion_longitude), delta_t)
+    };
+    vec![
+        planet("Mercury", 3.285e23, 0.38709927, 0.20563593, 252.25032350, 77.45779628),
+        planet("Venus", 4.867e24, 0.72333566, 0.00677672, 181.97909950, 131.60246718),
+        planet("Earth", 5.972e24, 1.00000261, 0.01671123, 100.46457166, 102.93768193),
+        planet("Mars", 6.417e23, 1.52371034, 0.09339410, -4.55343205, -23.94362959),
+        planet("Jupiter", 1.898e27, 5.20288700, 0.04838624, 34.39644051, 14.72847983),
+        planet("Saturn", 5.683e26, 9.53667594, 0.05386179, 49.95424423, 92.59887831),
+        planet("Uranus", 8.681e25, 19.18916464, 0.04725744, 313.23810451, 170.95427630),
+        planet("Neptune", 1.024e26, 30.06992276, 0.00859048, -55.12002969, 44.96476227),
+        planet("Pluto", 1.309e22, 39.48211675, 0.24882730, 238.92903833, 224.06891629), // Dwarf planet
+    ]
+}
 
 pub(crate) fn solar_system() -> Model {
     let restitution = 1.0;
@@ -7,99 +47,81 @@ pub(crate) fn solar_system() -> Model {
     let scale_factor = 1e-9;
     let g_constant = G_CONSTANT;
     let do_collisions = false;
-    let particles = vec![
-        Particle::new( // Sun
-                       [0.0, 0.0],
-                       [0.0, 0.0],
-                       1.989e30, // Mass of the Sun
-                       delta_t,
-                       String::from("Sun"),
-                       do_collisions,
-        ),
-        Particle::new( // Mercury
-                       [4.6e10, 0.0], // Perihelion distance in meters
-                       [0.0, 53703.3518507], // Orbital velocity in m/s
-                       3.285e23, // Mass of Mercury
-                       delta_t,
-                       String::from("Mercury"),
-                       do_collisions,
-        ),
-        Particle::new( // Venus
-                       [1.0875e11, 0.0], // Average distance from Sun in meters
-                       [0.0, 34927.3531777], // Orbital velocity in m/s
-                       4.867e24, // Mass of Venus
-                       delta_t,
-                       String::from("Venus"),
-                       do_collisions,
-        ),
-        Particle::new( // Earth
-                       [1.4765e11, 0.0], // Average distance from Sun in meters
-                       [0.0, 29975.3030751], // Orbital velocity in m/s
-                       5.972e24, // Mass of Earth
-                       delta_t,
-                       String::from("Earth"),
-                       do_collisions,
-        ),
-        Particle::new( // Mars
-                       [2.279e11, 0.0], // Average distance from Sun in meters
-                       [0.0, 24117.9259962], // Orbital velocity in m/s
-                       6.417e23, // Mass of Mars
-                       delta_t,
-                       String::from("Mars"),
-                       do_collisions,
-        ),
-        Particle::new( // Jupiter
-                       [7.785e11, 0.0], // Average distance from Sun in meters
-                       [0.0, 13069.708962], // Orbital velocity in m/s
-                       1.898e27, // Mass of Jupiter
-                       delta_t,
-                       String::from("Jupiter"),
-                       do_collisions,
-        ),
-        Particle::new( // Saturn
-                       [1.4335e12, 0.0], // Average distance from Sun in meters
-                       [0.0, 9690.4862238], // Orbital velocity in m/s
-                       5.683e26, // Mass of Saturn
-                       delta_t,
-                       String::from("Saturn"),
-                       do_collisions,
-        ),
-        Particle::new( // Uranus
-                       [2.8725e12, 0.0], // Average distance from Sun in meters
-                       [0.0, 6835.08288589], // Orbital velocity in m/s
-                       8.681e25, // Mass of Uranus
-                       delta_t,
-                       String::from("Uranus"),
-                       do_collisions,
-        ),
-        Particle::new( // Neptune
-                       [4.4951e12, 0.0], // Average distance from Sun in meters
-                       [0.0, 5477.9200121], // Orbital velocity in m/s
-                       1.024e26, // Mass of Neptune
-                       delta_t,
-                       String::from("Neptune"),
-                       do_collisions,
-        ),
-        Particle::new( // Pluto (Dwarf Planet)
-                       [5.9064e12, 0.0], // Average distance from Sun in meters
-                       [0.0, 4748.04182444], // Orbital velocity in m/s
-                       1.309e22, // Mass of Pluto
-                       delta_t,
-                       String::from("Pluto"),
-                       do_collisions,
-        ),
+    let sun = Particle::new(
+        [0.0, 0.0],
+        [0.0, 0.0],
+        1.989e30, // Mass of the Sun
+        delta_t,
+        String::from("Sun"),
+        do_collisions,
+    );
+    let particles = [vec![sun.clone()], planets(&sun, delta_t)].concat();
+    let mut model = Model::new(
+        particles,
+        delta_t,
+        scale_factor,
+        g_constant,
+        do_collisions,
+        restitution,
+        "Solar System".into()
+    );
+    model.units = UnitSystem::Astronomical;
+    model.epoch = Some(J2000);
+    model.start_at_barycentre();
+    model
+}
 
+// Planets with their major moons and two more dwarf planets; moons are placed about their parent planet
+pub(crate) fn extended_solar_system() -> Model {
+    let restitution = 1.0;
+    let delta_t = 600.0; // short enough for Io's 1.8 day orbit
+    let substeps = 200; // so a frame still covers about a day and a half
+    let scale_factor = 1e-9;
+    let g_constant = G_CONSTANT;
+    let do_collisions = false;
+    let sun = Particle::new(
+        [0.0, 0.0],
+        [0.0, 0.0],
+        1.989e30, // Mass of the Sun
+        delta_t,
+        String::from("Sun"),
+        do_collisions,
+    );
+    let mut particles = [vec![sun.clone()], planets(&sun, delta_t)].concat();
+    let index = |particles: &[Particle], name: &str| particles.iter().position(|particle| particle.name == name).unwrap();
 
+    // Moons: semi-major axis in km and eccentricity; their phases are illustrative
+    let moons = [
+        ("Earth", "Moon", 7.342e22, 384_400.0, 0.0549, 0.0),
+        ("Jupiter", "Io", 8.932e22, 421_700.0, 0.0041, 0.0),
+        ("Jupiter", "Europa", 4.800e22, 671_034.0, 0.0090, 90.0),
+        ("Jupiter", "Ganymede", 1.4819e23, 1_070_412.0, 0.0013, 180.0),
+        ("Jupiter", "Callisto", 1.0759e23, 1_882_709.0, 0.0074, 270.0),
+        ("Saturn", "Titan", 1.3452e23, 1_221_870.0, 0.0288, 0.0),
     ];
-    Model::new(
+    for (parent, name, mass, semi_major_axis, eccentricity, mean_longitude) in moons {
+        let parent = particles[index(&particles, parent)].clone();
+        particles.push(orbiting(&parent, name, mass, elements(semi_major_axis * 1.0e3, eccentricity, mean_longitude, 0.0), delta_t));
+    }
+
+    // Dwarf planets: longitude of perihelion from published elements, mean longitude illustrative
+    particles.push(orbiting(&sun, "Ceres", 9.3835e20, elements(2.7675 * ASTRONOMICAL_UNIT, 0.0758, 160.0, 153.9), delta_t));
+    particles.push(orbiting(&sun, "Eris", 1.6466e22, elements(67.86 * ASTRONOMICAL_UNIT, 0.4407, 200.0, 187.6), delta_t));
+
+    let mut model = Model::new(
         particles,
         delta_t,
         scale_factor,
         g_constant,
         do_collisions,
         restitution,
-        "Solar System".into()
-    )
+        "Extended Solar System".into()
+    );
+    model.substeps = substeps;
+    model.units = UnitSystem::Astronomical;
+    model.epoch = Some(J2000);
+    model.start_at_barycentre();
+    model
 }
 pub(crate) fn earth_moon_system() -> Model {
     let restitution = 1.0;
@@ -125,7 +147,7 @@ pub(crate) fn earth_moon_system() -> Model {
                        do_collisions,
         ),
     ];
-    Model::new(
+    let mut model = Model::new(
         particles,
         delta_t,
         scale_factor,
@@ -133,7 +155,9 @@ pub(crate) fn earth_moon_system() -> Model {
         do_collisions,
         restitution,
         "Earth-Moon System".into()
-    )
+    );
+    model.start_at_barycentre();
+    model
 }
 pub(crate) fn blank_system() -> Model {
     let restitution = 1.0;
@@ -152,3 +176,282 @@ pub(crate) fn blank_system() -> Model {
         "Custom".into()
     )
 }
+pub(crate) fn mercury_precession() -> Model {
+    let restitution = 1.0;
+    let delta_t = 5.0e3;
+    let scale_factor = 5e-9;
+    let g_constant = G_CONSTANT;
+    let do_collisions = false;
+    let particles = vec![
+        Particle::new( // Sun
+                       [0.0, 0.0],
+                       [0.0, 0.0],
+                       1.989e30, // Mass of the Sun
+                       delta_t,
+                       String::from("Sun"),
+                       do_collisions,
+        ),
+        Particle::new( // Mercury
+                       [4.60037e10, 0.0], // Perihelion distance in meters, a = 5.791e10, e = 0.2056
+                       [0.0, 58981.491148], // Perihelion velocity in m/s
+                       3.285e23, // Mass of Mercury
+                       delta_t,
+                       String::from("Mercury"),
+                       do_collisions,
+        ),
+    ];
+    let mut model = Model::new(
+        particles,
+        delta_t,
+        scale_factor,
+        g_constant,
+        do_collisions,
+        restitution,
+        "Mercury Precession".into()
+    );
+    model.relativistic = true;
+    model.units = UnitSystem::Astronomical;
+    model
+}
+pub(crate) fn lunar_transfer() -> Model {
+    parse_scenario(LUNAR_TRANSFER_SCENARIO).expect("built-in lunar transfer scenario is valid")
+}
+
+// Few-body problems are stated with G = 1; lengths and masses are scaled up by a million so even close
+// encounters stay far apart compared to the 5 m particle radius, which leaves velocities unchanged and
+// makes one time unit a million seconds
+const LENGTH_UNIT: f64 = 1.0e6;
+const MASS_UNIT: f64 = 1.0e6;
+
+fn few_body(bodies: &[(&str, [f64; 2], [f64; 2], f64)], delta_t: f64, scale_factor: f32, substeps: usize, name: &str) -> Model {
+    let do_collisions = false;
+    let particles = bodies
+        .iter()
+        .map(|(body, position, velocity, mass)| Particle::new(
+            [position[0] * LENGTH_UNIT, position[1] * LENGTH_UNIT],
+            *velocity,
+            mass * MASS_UNIT,
+            delta_t,
+            String::from(*body),
+            do_collisions,
+        ))
+        .collect();
+    let mut model = Model::new(particles, delta_t, scale_factor, 1.0, do_collisions, 1.0, name.into());
+    model.substeps = substeps;
+    model.units = UnitSystem::henon(&model);
+    model
+}
+
+// Chenciner and Montgomery's choreography: three equal masses chasing each other around a figure eight, period 6.3259
+pub(crate) fn figure_eight() -> Model {
+    few_body(
+        &[
+            ("A", [0.97000436, -0.24308753], [0.46620368, 0.43236573], 1.0),
+            ("B", [-0.97000436, 0.24308753], [0.46620368, 0.43236573], 1.0),
+            ("C", [0.0, 0.0], [-0.93240737, -0.86473146], 1.0),
+        ],
+        1.0e3,
+        3.0e-4,
+        10,
+        "Figure Eight",
+    )
+}
+
+// Burrau's Pythagorean problem: masses 3, 4 and 5 at rest on the corners of a 3-4-5 triangle, opposite the sides
+// of matching length. The exact solution ends with 4 and 5 leaving as a binary and 3 ejected; its close
+// encounters make the outcome very sensitive to the time step
+pub(crate) fn pythagorean_problem() -> Model {
+    few_body(
+        &[
+            ("3", [1.0, 3.0], [0.0, 0.0], 3.0),
+            ("4", [-2.0, -1.0], [0.0, 0.0], 4.0),
+            ("5", [1.0, -1.0], [0.0, 0.0], 5.0),
+        ],
+        100.0, // the close encounters need a short step
+        8.0e-5,
+        100,
+        "Pythagorean Problem",
+    )
+}
+
+// Lagrange's equilateral solution: masses 1, 2 and 3 on a unit triangle turning rigidly about their barycentre;
+// these masses fail Routh's criterion, so the triangle eventually breaks up
+pub(crate) fn lagrange_equilateral() -> Model {
+    let masses = [1.0, 2.0, 3.0];
+    let total_mass: f64 = masses.iter().sum();
+    let vertices = [[0.0, 0.0], [1.0, 0.0], [0.5, 3f64.sqrt() / 2.0]];
+    let barycentre = [
+        (0..3).map(|i| vertices[i][0] * masses[i]).sum::<f64>() / total_mass,
+        (0..3).map(|i| vertices[i][1] * masses[i]).sum::<f64>() / total_mass,
+    ];
+    let angular_velocity = (total_mass * MASS_UNIT / LENGTH_UNIT.powi(3)).sqrt(); // side of one length unit
+    let bodies: Vec<(&str, [f64; 2], [f64; 2], f64)> = (0..3)
+        .map(|i| {
+            let position = [vertices[i][0] - barycentre[0], vertices[i][1] - barycentre[1]];
+            let velocity = [
+                -angular_velocity * position[1] * LENGTH_UNIT,
+                angular_velocity * position[0] * LENGTH_UNIT,
+            ];
+            (["1", "2", "3"][i], position, velocity, masses[i])
+        })
+        .collect();
+    few_body(&bodies, 1.0e3, 3.0e-4, 10, "Lagrange Equilateral")
+}
+
+// Sun and Jupiter on a circular orbit with test bodies at and near the L4 and L5 points; the co-rotating
+// frame (O) shows them librating on tadpole orbits
+pub(crate) fn sun_jupiter_trojans() -> Model {
+    let restitution = 1.0;
+    let delta_t = 1.0e5;
+    let scale_factor = 4.5e-10;
+    let g_constant = G_CONSTANT;
+    let do_collisions = false;
+    let sun_mass = 1.989e30;
+    let jupiter_mass = 1.898e27;
+    let total_mass = sun_mass + jupiter_mass;
+    let separation = 5.2029 * ASTRONOMICAL_UNIT;
+    let angular_velocity = (g_constant * total_mass / separation.powi(3)).sqrt();
+
+    let sun = [-separation * jupiter_mass / total_mass, 0.0];
+    // Trojans by angle from Jupiter about the Sun and distance from the Sun, in units of the separation
+    let mut bodies = vec![
+        ("Sun", sun, sun_mass),
+        ("Jupiter", [sun[0] + separation, 0.0], jupiter_mass),
+    ];
+    for (name, angle, distance) in [
+        ("L4", 60.0f64, 1.0),
+        ("L4 + 1%", 60.0, 1.01),
+        ("L5", -60.0, 1.0),
+        ("L5 + 10 deg", -50.0, 1.0),
+    ] {
+        let angle = angle.to_radians();
+        let position = [
+            sun[0] + separation * distance * angle.cos(),
+            separation * distance * angle.sin(),
+        ];
+        bodies.push((name, position, 1.0e10));
+    }
+    let mut particles: Vec<Particle> = bodies
+        .into_iter()
+        .map(|(name, position, mass)| Particle::new(
+            position,
+            [-angular_velocity * position[1], angular_velocity * position[0]],
+            mass,
+            delta_t,
+            String::from(name),
+            do_collisions,
+        ))
+        .collect();
+    // The Trojans feel the Sun and Jupiter but pull on nothing, making this the restricted three-body problem
+    for particle in particles.iter_mut().skip(2) {
+        particle.kind = ParticleKind::Test;
+    }
+    let mut model = Model::new(
+        particles,
+        delta_t,
+        scale_factor,
+        g_constant,
+        do_collisions,
+        restitution,
+        "Sun-Jupiter Trojans".into()
+    );
+    model.substeps = 20;
+    model.units = UnitSystem::Astronomical;
+    model
+}
+
+// Alpha Centauri A and B with planets on either side of their stability limits, which shed the unstable ones
+pub(crate) fn binary_star_stability() -> Model {
+    let planet = |name: &str, host: PlanetHost, critical_ratio: f64, phase: f64| BinaryPlanet {
+        name: name.into(),
+        mass: 5.972e24,
+        host,
+        critical_ratio,
+        phase,
+    };
+    let binary = BinaryStar {
+        primary_mass: 1.1 * SOLAR_MASS,
+        secondary_mass: 0.907 * SOLAR_MASS,
+        separation: 23.4 * ASTRONOMICAL_UNIT,
+        eccentricity: 0.52,
+        planets: vec![
+            planet("S stable", PlanetHost::Primary, 0.7, 0.0),
+            planet("S unstable", PlanetHost::Secondary, 1.6, 180.0),
+            planet("P stable", PlanetHost::Circumbinary, 1.2, 90.0),
+            planet("P unstable", PlanetHost::Circumbinary, 0.7, 270.0),
+        ],
+    };
+    binary_star_model(&binary, "Binary Star Stability")
+}
+
+// The solar system with a main belt of test particles; Jupiter's resonances slowly open the Kirkwood gaps
+// near 2.50 (3:1), 2.82 (5:2), 2.95 (7:3) and 3.28 AU (2:1)
+pub(crate) fn main_belt() -> Model {
+    let mut model = solar_system();
+    let belt = Belt {
+        primary: 0,
+        count: 1500,
+        inner_radius: 2.0 * ASTRONOMICAL_UNIT,
+        outer_radius: 3.6 * ASTRONOMICAL_UNIT,
+        density_exponent: -1.0,
+        eccentricity_dispersion: 0.1,
+        mass: 1.0,
+        test_particles: true,
+        seed: 1,
+    };
+    let asteroids = belt_particles(&model.particles, &belt, model.g_constant, model.delta_t);
+    model.particles.extend(asteroids.iter().cloned());
+    model.default_particles.extend(asteroids);
+    model.name = "Main Belt".into();
+    model.substeps = 20;
+    model
+}
+
+// Saturn's main rings and F ring with the shepherd moons Prometheus and Pandora either side of the F ring,
+// and Mimas, whose 2:1 resonance sits at the Cassini division
+pub(crate) fn ringed_planet() -> Model {
+    let restitution = 1.0;
+    let delta_t = 100.0;
+    let scale_factor = 2.5e-6;
+    let g_constant = G_CONSTANT;
+    let do_collisions = false;
+    let saturn = Particle::new([0.0, 0.0], [0.0, 0.0], 5.683e26, delta_t, String::from("Saturn"), do_collisions);
+    let moon = |name: &str, mass: f64, semi_major_axis: f64, mean_longitude: f64| {
+        orbiting(&saturn, name, mass, elements(semi_major_axis, 0.0, mean_longitude, 0.0), delta_t)
+    };
+    let mut particles = vec![
+        saturn.clone(),
+        moon("Prometheus", 1.6e17, 1.3938e8, 0.0),
+        moon("Pandora", 1.37e17, 1.4172e8, 180.0),
+        moon("Mimas", 3.75e19, 1.8554e8, 90.0),
+    ];
+
+    let ring = |count: usize, inner_radius: f64, outer_radius: f64, seed: u64| Belt {
+        primary: 0,
+        count,
+        inner_radius,
+        outer_radius,
+        density_exponent: 0.0,
+        eccentricity_dispersion: 1e-4,
+        mass: 1.0,
+        test_particles: true,
+        seed,
+    };
+    let rings = [ring(1500, 7.45e7, 1.368e8, 1), ring(300, 1.399e8, 1.405e8, 2)]; // C, B and A rings, and the F ring
+    for ring in &rings {
+        let ring_particles = belt_particles(&particles, ring, g_constant, delta_t);
+        particles.extend(ring_particles);
+    }
+
+    let mut model = Model::new(
+        particles,
+        delta_t,
+        scale_factor,
+        g_constant,
+        do_collisions,
+        restitution,
+        "Ringed Planet".into()
+    );
+    model.substeps = 20;
+    model
+}
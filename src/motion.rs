@@ -0,0 +1,99 @@
+use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+
+// How a particle's position is advanced each step
+#[derive(Clone, Deserialize, Serialize)]
+pub(crate) enum Motion {
+    Dynamic,                        // integrated from the forces acting on it
+    Pinned,                         // held in place
+    Kinematic(KinematicPath),       // driven along a prescribed path
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum KinematicPath {
+    Circular {
+        center: [f64; 2],           // meters
+        radius: f64,                // meters
+        period: f64,                // seconds, negative for clockwise on screen
+        phase: f64,                 // radians at time zero
+    },
+    Keyframes {
+        keyframes: Vec<Keyframe>,   // sorted by time, linearly interpolated
+    },
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Keyframe {
+    pub(crate) time: f64,           // seconds
+    pub(crate) position: [f64; 2],  // meters
+}
+
+impl Motion {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Motion::Dynamic => "Dynamic",
+            Motion::Pinned => "Pinned",
+            Motion::Kinematic(_) => "Kinematic",
+        }
+    }
+}
+
+// Checks the values serde cannot, e.g. a zero period or keyframes out of order
+pub(crate) fn validate_path(path: &KinematicPath) -> Result<(), String> {
+    match path {
+        KinematicPath::Circular { center, radius, period, phase } => {
+            if !(center[0].is_finite() && center[1].is_finite() && phase.is_finite()) {
+                return Err("center and phase must be finite".into());
+            }
+            if !(radius.is_finite() && *radius > 0.0) {
+                return Err("radius must be a positive number".into());
+            }
+            if !(period.is_finite() && *period != 0.0) {
+                return Err("period must be a finite, non-zero number".into());
+            }
+        }
+        KinematicPath::Keyframes { keyframes } => {
+            if keyframes.is_empty() {
+                return Err("keyframes must not be empty".into());
+            }
+            if keyframes.iter().any(|keyframe| !(keyframe.time.is_finite() && keyframe.position[0].is_finite() && keyframe.position[1].is_finite())) {
+                return Err("keyframe times and positions must be finite".into());
+            }
+            if keyframes.windows(2).any(|pair| pair[1].time <= pair[0].time) {
+                return Err("keyframe times must be strictly increasing".into());
+            }
+        }
+    }
+    Ok(())
+}
+
+impl KinematicPath {
+    pub(crate) fn position_at(&self, time: f64) -> [f64; 2] {
+        match self {
+            KinematicPath::Circular { center, radius, period, phase } => {
+                let angle = phase + 2.0 * PI * time / period;
+                [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+            }
+            KinematicPath::Keyframes { keyframes } => {
+                let Some(first) = keyframes.first() else {
+                    return [0.0, 0.0];
+                };
+                if time <= first.time {
+                    return first.position;
+                }
+                for pair in keyframes.windows(2) {
+                    if time <= pair[1].time {
+                        let fraction = (time - pair[0].time) / (pair[1].time - pair[0].time);
+                        return [
+                            pair[0].position[0] + (pair[1].position[0] - pair[0].position[0]) * fraction,
+                            pair[0].position[1] + (pair[1].position[1] - pair[0].position[1]) * fraction,
+                        ];
+                    }
+                }
+                keyframes[keyframes.len() - 1].position
+            }
+        }
+    }
+}
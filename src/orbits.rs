@@ -0,0 +1,129 @@
+use std::f64::consts::PI;
+use crate::frames::rotate;
+
+// Keplerian elements of an orbit in the plane; angles in radians, measured counter-clockwise from +x
+#[derive(Clone, Copy)]
+pub(crate) struct OrbitalElements {
+    pub(crate) semi_major_axis: f64,        // meters, magnitude taken for hyperbolic orbits
+    pub(crate) eccentricity: f64,           // 0 circular, below 1 elliptic, above 1 hyperbolic
+    pub(crate) argument_of_periapsis: f64,  // direction of periapsis
+    pub(crate) mean_anomaly: f64,           // at the time the state is wanted
+}
+
+impl OrbitalElements {
+    // Position and velocity relative to the primary, with mu = G (M + m)
+    pub(crate) fn state(&self, gravitational_parameter: f64) -> ([f64; 2], [f64; 2]) {
+        let e = self.eccentricity;
+        let semi_latus_rectum = self.semi_major_axis.abs() * (1.0 - e * e).abs();
+        let true_anomaly = true_anomaly_from_mean(self.mean_anomaly, e);
+        state_from_true_anomaly(gravitational_parameter, semi_latus_rectum, e, self.argument_of_periapsis, true_anomaly)
+    }
+}
+
+// Eccentric anomaly for elliptic orbits, hyperbolic anomaly for hyperbolic ones, by Newton's method
+pub(crate) fn solve_kepler(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let e = eccentricity;
+    if e < 1.0 {
+        let m = mean_anomaly.rem_euclid(2.0 * PI);
+        let mut anomaly = if e < 0.8 { m } else { PI };
+        for _ in 0..50 {
+            let step = (anomaly - e * anomaly.sin() - m) / (1.0 - e * anomaly.cos());
+            anomaly -= step;
+            if step.abs() < 1e-14 {
+                break;
+            }
+        }
+        anomaly
+    } else {
+        let m = mean_anomaly;
+        let mut anomaly = m.signum() * (2.0 * m.abs() / e + 1.8).ln();
+        for _ in 0..50 {
+            let step = (e * anomaly.sinh() - anomaly - m) / (e * anomaly.cosh() - 1.0);
+            anomaly -= step;
+            if step.abs() < 1e-14 {
+                break;
+            }
+        }
+        anomaly
+    }
+}
+
+pub(crate) fn true_anomaly_from_mean(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let e = eccentricity;
+    let anomaly = solve_kepler(mean_anomaly, e);
+    if e < 1.0 {
+        2.0 * ((1.0 + e).sqrt() * (anomaly / 2.0).sin()).atan2((1.0 - e).sqrt() * (anomaly / 2.0).cos())
+    } else {
+        2.0 * (((e + 1.0) / (e - 1.0)).sqrt() * (anomaly / 2.0).tanh()).atan()
+    }
+}
+
+// Relative state at a true anomaly; the semi-latus rectum keeps this valid for parabolic orbits too
+pub(crate) fn state_from_true_anomaly(
+    gravitational_parameter: f64,
+    semi_latus_rectum: f64,
+    eccentricity: f64,
+    argument_of_periapsis: f64,
+    true_anomaly: f64,
+) -> ([f64; 2], [f64; 2]) {
+    let (sin, cos) = true_anomaly.sin_cos();
+    let radius = semi_latus_rectum / (1.0 + eccentricity * cos);
+    let speed = (gravitational_parameter / semi_latus_rectum).sqrt();
+    (
+        rotate([radius * cos, radius * sin], argument_of_periapsis),
+        rotate([-speed * sin, speed * (eccentricity + cos)], argument_of_periapsis),
+    )
+}
+
+// Osculating orbit of a body about a primary, as read back from its current state
+pub(crate) struct Orbit {
+    pub(crate) elements: OrbitalElements,   // semi-major axis negative for hyperbolic orbits
+    pub(crate) true_anomaly: f64,           // radians from periapsis
+    pub(crate) period: Option<f64>,         // seconds, bound orbits only
+    pub(crate) periapsis: f64,              // meters
+    pub(crate) apoapsis: Option<f64>,       // meters, bound orbits only
+    pub(crate) retrograde: bool,            // clockwise about the primary
+}
+
+// Cartesian to Keplerian conversion from the relative position and velocity, with mu = G (M + m)
+pub(crate) fn orbit_from_state(gravitational_parameter: f64, position: [f64; 2], velocity: [f64; 2]) -> Orbit {
+    let mu = gravitational_parameter;
+    let radius = position[0].hypot(position[1]);
+    let speed_squared = velocity[0].powi(2) + velocity[1].powi(2);
+    let radial_velocity = position[0] * velocity[0] + position[1] * velocity[1];
+    let angular_momentum = position[0] * velocity[1] - position[1] * velocity[0];
+    let direction = if angular_momentum < 0.0 { -1.0 } else { 1.0 };
+
+    let eccentricity_vector = [
+        ((speed_squared - mu / radius) * position[0] - radial_velocity * velocity[0]) / mu,
+        ((speed_squared - mu / radius) * position[1] - radial_velocity * velocity[1]) / mu,
+    ];
+    let eccentricity = eccentricity_vector[0].hypot(eccentricity_vector[1]);
+    let semi_major_axis = 1.0 / (2.0 / radius - speed_squared / mu);
+    let semi_latus_rectum = angular_momentum.powi(2) / mu;
+
+    // Periapsis is undefined on a circular orbit, so angles are then measured from +x
+    let argument_of_periapsis = if eccentricity > 1e-12 { eccentricity_vector[1].atan2(eccentricity_vector[0]) } else { 0.0 };
+    let true_anomaly = (direction * (position[1].atan2(position[0]) - argument_of_periapsis)).rem_euclid(2.0 * PI);
+
+    let mean_anomaly = if eccentricity < 1.0 {
+        let eccentric_anomaly = 2.0 * ((1.0 - eccentricity).sqrt() * (true_anomaly / 2.0).sin())
+            .atan2((1.0 + eccentricity).sqrt() * (true_anomaly / 2.0).cos());
+        (eccentric_anomaly - eccentricity * eccentric_anomaly.sin()).rem_euclid(2.0 * PI)
+    } else if eccentricity > 1.0 {
+        let hyperbolic_anomaly = 2.0 * (((eccentricity - 1.0) / (eccentricity + 1.0)).sqrt() * (true_anomaly / 2.0).tan()).atanh();
+        eccentricity * hyperbolic_anomaly.sinh() - hyperbolic_anomaly
+    } else {
+        0.0
+    };
+
+    let bound = eccentricity < 1.0;
+    Orbit {
+        elements: OrbitalElements { semi_major_axis, eccentricity, argument_of_periapsis, mean_anomaly },
+        true_anomaly,
+        period: bound.then(|| 2.0 * PI * (semi_major_axis.powi(3) / mu).sqrt()),
+        periapsis: semi_latus_rectum / (1.0 + eccentricity),
+        apoapsis: bound.then(|| semi_latus_rectum / (1.0 - eccentricity)),
+        retrograde: angular_momentum < 0.0,
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, Write};
+use std::path::{Path, PathBuf};
+use crate::structs::Model;
+use crate::units::{Quantity, UnitSystem};
+
+pub(crate) const RECORDING_MAGIC: &[u8; 8] = b"GRAVREC\0";
+pub(crate) const INDEX_MAGIC: &[u8; 8] = b"GRAVIDX\0";
+pub(crate) const RECORDING_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum RecordFormat {
+    Csv,        // one row per particle per recorded step, in the model's display units
+    Binary,     // little-endian model values with an index of frames at the end, for large runs
+}
+
+impl RecordFormat {
+    // Binary unless the path ends in .csv
+    pub(crate) fn for_path(path: &Path) -> Self {
+        if path.extension().is_some_and(|extension| extension == "csv") { RecordFormat::Csv } else { RecordFormat::Binary }
+    }
+
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::Csv => "csv",
+            RecordFormat::Binary => "grec",
+        }
+    }
+}
+
+// Streams every Nth step of all particles to disk.
+//
+// The binary layout is a header (magic, version, model name, delta_t, scale factor, point size, collisions,
+// units and epoch), then one frame per recorded step (step, time, particle count, and x, y, vx, vy, mass for
+// each particle), then a particle table (name, radius, test flag) and the frame index (step, time, offset),
+// and finally the offset of the particle table and the index magic.
+pub(crate) struct Recorder {
+    pub(crate) path: PathBuf,
+    format: RecordFormat,
+    every: u64,                             // steps between recorded frames
+    pub(crate) frames: u64,                 // frames written so far
+    writer: BufWriter<File>,
+    position: u64,                          // bytes written, as seeking the writer would flush it
+    index: Vec<(u64, f64, u64)>,            // step, time and offset of each binary frame
+    units: UnitSystem,
+}
+
+impl Recorder {
+    // Starts a recording with the model's current state as its first frame
+    pub(crate) fn create(path: &Path, format: RecordFormat, every: u64, model: &Model) -> io::Result<Self> {
+        if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            fs::create_dir_all(directory)?;
+        }
+        let mut recorder = Recorder {
+            path: path.to_path_buf(),
+            format,
+            every: every.max(1),
+            frames: 0,
+            writer: BufWriter::new(File::create(path)?),
+            position: 0,
+            index: Vec::new(),
+            units: model.units,
+        };
+        match format {
+            RecordFormat::Csv => recorder.write_csv_header()?,
+            RecordFormat::Binary => {
+                recorder.write_binary_header(model)?;
+                recorder.position = recorder.writer.stream_position()?;
+            }
+        }
+        recorder.write_frame(model)?;
+        Ok(recorder)
+    }
+
+    // Call after every step; only every Nth step is written
+    pub(crate) fn record(&mut self, model: &Model) -> io::Result<()> {
+        if model.steps.is_multiple_of(self.every) {
+            self.write_frame(model)?;
+        }
+        Ok(())
+    }
+
+    // Writes the particle table and index of a binary recording; the file is complete once this returns
+    pub(crate) fn finish(mut self, model: &Model) -> io::Result<PathBuf> {
+        if self.format == RecordFormat::Binary {
+            let table_offset = self.position;
+            write_u32(&mut self.writer, model.particles.len() as u32)?;
+            for particle in &model.particles {
+                write_string(&mut self.writer, &particle.name)?;
+                write_f64(&mut self.writer, particle.radius)?;
+                self.writer.write_all(&[particle.is_test() as u8])?;
+            }
+            write_u64(&mut self.writer, self.index.len() as u64)?;
+            for &(step, time, offset) in &self.index {
+                write_u64(&mut self.writer, step)?;
+                write_f64(&mut self.writer, time)?;
+                write_u64(&mut self.writer, offset)?;
+            }
+            write_u64(&mut self.writer, table_offset)?;
+            self.writer.write_all(INDEX_MAGIC)?;
+        }
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+
+    fn write_csv_header(&mut self) -> io::Result<()> {
+        let column = |name: &str, quantity| match self.units.symbol(quantity) {
+            "" => name.to_string(),
+            symbol => format!("{} ({})", name, symbol),
+        };
+        writeln!(
+            self.writer,
+            "step,{},id,name,{},{},{},{},{}",
+            column("time", Quantity::Time),
+            column("x", Quantity::Length),
+            column("y", Quantity::Length),
+            column("vx", Quantity::Velocity),
+            column("vy", Quantity::Velocity),
+            column("mass", Quantity::Mass),
+        )
+    }
+
+    fn write_binary_header(&mut self, model: &Model) -> io::Result<()> {
+        self.writer.write_all(RECORDING_MAGIC)?;
+        write_u32(&mut self.writer, RECORDING_VERSION)?;
+        write_string(&mut self.writer, &model.name)?;
+        write_f64(&mut self.writer, model.delta_t)?;
+        self.writer.write_all(&model.scale_factor.to_le_bytes())?;
+        self.writer.write_all(&model.point_size.to_le_bytes())?;
+        self.writer.write_all(&[model.do_collisions as u8])?;
+        let (tag, length, time, mass) = match model.units {
+            UnitSystem::Si => (0, 1.0, 1.0, 1.0),
+            UnitSystem::Astronomical => (1, 1.0, 1.0, 1.0),
+            UnitSystem::Henon { length, mass, time } => (2, length, time, mass),
+            UnitSystem::Model => (3, 1.0, 1.0, 1.0),
+        };
+        self.writer.write_all(&[tag])?;
+        for value in [length, time, mass] {
+            write_f64(&mut self.writer, value)?;
+        }
+        write_f64(&mut self.writer, model.epoch.unwrap_or(f64::NAN))
+    }
+
+    fn write_frame(&mut self, model: &Model) -> io::Result<()> {
+        match self.format {
+            RecordFormat::Csv => {
+                let units = self.units;
+                let time = units.value_in(model.elapsed_time, Quantity::Time);
+                for (id, particle) in model.particles.iter().enumerate() {
+                    let velocity = particle.velocity(model.delta_t);
+                    writeln!(
+                        self.writer,
+                        "{},{},{},{},{},{},{},{},{}",
+                        model.steps,
+                        time,
+                        id,
+                        csv_field(&particle.name),
+                        units.value_in(particle.position[0], Quantity::Length),
+                        units.value_in(particle.position[1], Quantity::Length),
+                        units.value_in(velocity[0], Quantity::Velocity),
+                        units.value_in(velocity[1], Quantity::Velocity),
+                        units.value_in(particle.mass, Quantity::Mass),
+                    )?;
+                }
+            }
+            RecordFormat::Binary => {
+                self.index.push((model.steps, model.elapsed_time, self.position));
+                self.position += 20 + 40 * model.particles.len() as u64;
+                write_u64(&mut self.writer, model.steps)?;
+                write_f64(&mut self.writer, model.elapsed_time)?;
+                write_u32(&mut self.writer, model.particles.len() as u32)?;
+                for particle in &model.particles {
+                    let velocity = particle.velocity(model.delta_t);
+                    for value in [particle.position[0], particle.position[1], velocity[0], velocity[1], particle.mass] {
+                        write_f64(&mut self.writer, value)?;
+                    }
+                }
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+// Quotes names holding commas or quotes
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_f64(writer: &mut impl Write, value: f64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_string(writer: &mut impl Write, text: &str) -> io::Result<()> {
+    write_u32(writer, text.len() as u32)?;
+    writer.write_all(text.as_bytes())
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use macroquad::color::{Color, RED, WHITE, YELLOW};
+use macroquad::prelude::{draw_rectangle, draw_text, screen_height, screen_width};
+use crate::generators::*;
+use crate::models::*;
+use crate::scenario::{load_scenario, ScenarioError};
+use crate::structs::Model;
+
+pub(crate) const PAGE_SIZE: usize = 9; // Ctrl+1..9
+const SOLAR_MASS: f64 = 1.989e30;       // kilograms
+const PARSEC: f64 = 3.0857e16;          // meters
+
+enum ScenarioSource {
+    Builtin(fn() -> Model),
+    File(PathBuf),
+}
+
+// A built-in model with what the picker shows of it, so listing the models builds none of them
+struct Builtin {
+    name: &'static str,
+    particle_count: usize,
+    build: fn() -> Model,
+    scenario: Option<&'static str>,         // text of the scenario file the model is read from
+}
+
+const fn builtin(name: &'static str, particle_count: usize, build: fn() -> Model) -> Builtin {
+    Builtin { name, particle_count, build, scenario: None }
+}
+
+static BUILTINS: [Builtin; 17] = [
+    builtin("Custom", 0, blank_system),
+    builtin("Solar System", 10, solar_system),
+    builtin("Earth-Moon System", 2, earth_moon_system),
+    builtin("Mercury Precession", 2, mercury_precession),
+    Builtin { name: "Lunar Transfer", particle_count: 3, build: lunar_transfer, scenario: Some(LUNAR_TRANSFER_SCENARIO) },
+    builtin("Extended Solar System", 18, extended_solar_system),
+    builtin("Figure Eight", 3, figure_eight),
+    builtin("Pythagorean Problem", 3, pythagorean_problem),
+    builtin("Lagrange Equilateral", 3, lagrange_equilateral),
+    builtin("Sun-Jupiter Trojans", 6, sun_jupiter_trojans),
+    builtin("Binary Star Stability", 6, binary_star_stability),
+    builtin("Main Belt", 1510, main_belt),
+    builtin("Ringed Planet", 1804, ringed_planet),
+    builtin("Plummer Sphere", 400, || plummer_sphere(400, 1.0e4 * SOLAR_MASS, PARSEC, 1)),
+    builtin("King Model", 400, || king_model(400, 1.0e4 * SOLAR_MASS, 0.5 * PARSEC, 6.0, 1)),
+    builtin("Exponential Disk", 401, || exponential_disk(400, 1.0e10 * SOLAR_MASS, 3.0e3 * PARSEC, 1.0e10 * SOLAR_MASS, 1)),
+    builtin("Galaxy Merger", 602, || galaxy_merger(GalaxyEncounter {
+        count: 300,
+        primary_mass: 1.0e11 * SOLAR_MASS,
+        mass_ratio: 1.0,
+        scale_radius: 3.0e3 * PARSEC,
+        pericentre: 1.5e4 * PARSEC,
+        eccentricity: 1.0,
+        start_distance: 4.0e4 * PARSEC,
+        primary_prograde: true,
+        secondary_prograde: true,
+        seed: 1,
+    })),
+];
+
+pub(crate) struct ScenarioEntry {
+    pub(crate) name: String,
+    pub(crate) particle_count: usize,
+    source: ScenarioSource,
+}
+
+// Built-in models followed by the scenario files found in a directory, paged for Ctrl+1..9
+pub(crate) struct ScenarioRegistry {
+    pub(crate) entries: Vec<ScenarioEntry>,
+    pub(crate) errors: Vec<String>,         // scenario files that failed to load
+    pub(crate) page: usize,
+    directory: PathBuf,
+}
+
+impl ScenarioRegistry {
+    pub(crate) fn new(directory: &Path) -> Self {
+        let mut registry = ScenarioRegistry {
+            entries: Vec::new(),
+            errors: Vec::new(),
+            page: 0,
+            directory: directory.to_path_buf(),
+        };
+        registry.scan();
+        registry
+    }
+
+    pub(crate) fn scan(&mut self) {
+        self.entries.clear();
+        self.errors.clear();
+
+        for builtin in BUILTINS.iter() {
+            self.entries.push(ScenarioEntry {
+                name: builtin.name.to_string(),
+                particle_count: builtin.particle_count,
+                source: ScenarioSource::Builtin(builtin.build),
+            });
+        }
+
+        let mut paths: Vec<PathBuf> = match fs::read_dir(&self.directory) {
+            Ok(directory) => directory
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort();
+
+        for path in paths {
+            // Built-in models read from a scenario file are already listed
+            if fs::read_to_string(&path).is_ok_and(|text| BUILTINS.iter().any(|builtin| builtin.scenario == Some(text.as_str()))) {
+                continue;
+            }
+            match load_scenario(&path) {
+                Ok(model) if self.entries.iter().any(|entry| entry.name == model.name) => {
+                    self.errors.push(format!("{}: another model is already named {}, rename it to list it", path.display(), model.name));
+                }
+                Ok(model) => self.entries.push(ScenarioEntry {
+                    name: model.name,
+                    particle_count: model.particles.len(),
+                    source: ScenarioSource::File(path),
+                }),
+                Err(error) => self.errors.push(error.to_string()),
+            }
+        }
+        self.page = self.page.min(self.page_count() - 1);
+    }
+
+    pub(crate) fn page_count(&self) -> usize {
+        self.entries.len().div_ceil(PAGE_SIZE).max(1)
+    }
+
+    pub(crate) fn change_page(&mut self, offset: isize) {
+        let pages = self.page_count() as isize;
+        self.page = (self.page as isize + offset).rem_euclid(pages) as usize;
+    }
+
+    // Entry bound to Ctrl+slot on the current page, slot counting from 1
+    pub(crate) fn load_slot(&self, slot: usize) -> Option<Result<Model, ScenarioError>> {
+        let index = self.page * PAGE_SIZE + slot - 1;
+        (index < self.entries.len()).then(|| self.load(index))
+    }
+
+    pub(crate) fn load_named(&self, name: &str) -> Option<Result<Model, ScenarioError>> {
+        let index = self.entries.iter().position(|entry| entry.name == name)?;
+        Some(self.load(index))
+    }
+
+    fn load(&self, index: usize) -> Result<Model, ScenarioError> {
+        match &self.entries[index].source {
+            ScenarioSource::Builtin(builtin) => Ok(builtin()),
+            ScenarioSource::File(path) => load_scenario(path),
+        }
+    }
+
+    pub(crate) fn draw_picker(&self, selected_name: &str) {
+        let width = 420.0;
+        let height = 80.0 + PAGE_SIZE as f32 * 22.0 + self.errors.len().min(3) as f32 * 20.0;
+        let left = (screen_width() - width) / 2.0;
+        let top = (screen_height() - height) / 2.0;
+        draw_rectangle(left, top, width, height, Color::new(0.0, 0.0, 0.0, 0.85));
+
+        draw_text(&format!("Models (page {} of {}, PageUp/PageDown)", self.page + 1, self.page_count()), left + 15.0, top + 25.0, 18.0, YELLOW);
+        let mut y = top + 55.0;
+        for slot in 1..=PAGE_SIZE {
+            let Some(entry) = self.entries.get(self.page * PAGE_SIZE + slot - 1) else {
+                break;
+            };
+            let color = if entry.name == selected_name { RED } else { WHITE };
+            draw_text(&format!("Ctrl+{}  {} ({} particles)", slot, entry.name, entry.particle_count), left + 15.0, y, 16.0, color);
+            y += 22.0;
+        }
+        for error in self.errors.iter().take(3) {
+            draw_text(error.lines().next().unwrap_or(""), left + 15.0, y + 10.0, 14.0, RED);
+            y += 20.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_names_and_counts_match_their_models() {
+        for builtin in BUILTINS.iter() {
+            let model = (builtin.build)();
+            assert_eq!(model.name, builtin.name);
+            assert_eq!(model.particles.len(), builtin.particle_count, "{}", builtin.name);
+        }
+    }
+}
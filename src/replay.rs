@@ -0,0 +1,512 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use macroquad::prelude::*;
+use crate::clock::{calendar_date, current_julian_date, elapsed_text};
+use crate::recorder::{RecordFormat, INDEX_MAGIC, RECORDING_MAGIC, RECORDING_VERSION};
+use crate::structs::{Model, Particle, ParticleKind};
+use crate::units::{Quantity, UnitSystem};
+use crate::util::round_to_place;
+
+const TIMELINE_MARGIN: f32 = 20.0;     // pixels either side of the scrubber
+const TIMELINE_HEIGHT: f32 = 10.0;     // pixels
+
+enum FrameSource {
+    Binary { file: BufReader<File>, file_length: u64, offsets: Vec<u64> },
+    Csv { frames: Vec<Vec<[f64; 5]>> },             // x, y, vx, vy and mass of each particle, in model values
+}
+
+// A recorded run, shown one frame at a time through a model that is drawn but never stepped
+pub(crate) struct Recording {
+    pub(crate) model: Model,
+    pub(crate) frames: Vec<(u64, f64)>,             // step and time of each frame
+    pub(crate) current: usize,
+    particles: Vec<Particle>,                       // every particle the recording knows, in recorded order
+    source: FrameSource,
+}
+
+impl Recording {
+    pub(crate) fn open(path: &Path) -> Result<Self, String> {
+        let error = |error: io::Error| format!("could not replay {}: {}", path.display(), error);
+        let mut recording = match RecordFormat::for_path(path) {
+            RecordFormat::Binary => open_binary(path).map_err(error)?,
+            RecordFormat::Csv => open_csv(path).map_err(error)?,
+        };
+        if recording.frames.is_empty() {
+            return Err(format!("could not replay {}: it has no frames", path.display()));
+        }
+        recording.show_frame(0).map_err(error)?;
+        Ok(recording)
+    }
+
+    // Moves the model's particles to a recorded frame
+    pub(crate) fn show_frame(&mut self, index: usize) -> io::Result<()> {
+        let states = match &mut self.source {
+            FrameSource::Binary { file, file_length, offsets } => {
+                file.seek(SeekFrom::Start(offsets[index] + 16))?;
+                let count = read_u32(file)? as usize;
+                check_length(file, *file_length, 40 * count as u64)?;
+                let mut states = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let mut state = [0.0; 5];
+                    for value in state.iter_mut() {
+                        *value = read_f64(file)?;
+                    }
+                    states.push(state);
+                }
+                states
+            }
+            FrameSource::Csv { frames } => frames[index].clone(),
+        };
+
+        let delta_t = self.model.delta_t;
+        while self.particles.len() < states.len() {
+            self.particles.push(Particle::new([0.0, 0.0], [0.0, 0.0], 1.0, delta_t, "/".into(), self.model.do_collisions));
+        }
+        self.model.particles = states
+            .iter()
+            .zip(&self.particles)
+            .map(|(state, template)| {
+                let mut particle = template.clone();
+                particle.position = [state[0], state[1]];
+                particle.previous_position = [state[0] - state[2] * delta_t, state[1] - state[3] * delta_t];
+                particle.mass = state[4];
+                particle
+            })
+            .collect();
+        (self.model.steps, self.model.elapsed_time) = self.frames[index];
+        self.current = index;
+        Ok(())
+    }
+}
+
+fn open_binary(path: &Path) -> io::Result<Recording> {
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason.to_string());
+    let mut file = BufReader::new(File::open(path)?);
+    let file_length = file.get_ref().metadata()?.len();
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != RECORDING_MAGIC {
+        return Err(invalid("not a binary recording"));
+    }
+    if read_u32(&mut file)? != RECORDING_VERSION {
+        return Err(invalid("unsupported recording version"));
+    }
+    let name = read_string(&mut file, file_length)?;
+    let delta_t = read_f64(&mut file)?;
+    let scale_factor = read_f32(&mut file)?;
+    let point_size = read_f32(&mut file)?;
+    let do_collisions = read_u8(&mut file)? != 0;
+    let tag = read_u8(&mut file)?;
+    let [length, time, mass] = [read_f64(&mut file)?, read_f64(&mut file)?, read_f64(&mut file)?];
+    let units = match tag {
+        0 => UnitSystem::Si,
+        1 => UnitSystem::Astronomical,
+        2 => UnitSystem::Henon { length, mass, time },
+        _ => UnitSystem::Model,
+    };
+    let epoch = read_f64(&mut file)?;
+    let frames_start = file.stream_position()?;
+
+    let mut particles = Vec::new();
+    let mut frames = Vec::new();
+    let mut offsets = Vec::new();
+    file.seek(SeekFrom::End(-16))?;
+    let table_offset = read_u64(&mut file)?;
+    file.read_exact(&mut magic)?;
+    if &magic == INDEX_MAGIC {
+        file.seek(SeekFrom::Start(table_offset))?;
+        let count = read_u32(&mut file)?;
+        check_length(&mut file, file_length, 13 * count as u64)?;
+        for _ in 0..count {
+            let name = read_string(&mut file, file_length)?;
+            let radius = read_f64(&mut file)?;
+            let mut particle = Particle::new([0.0, 0.0], [0.0, 0.0], 1.0, delta_t, name, do_collisions);
+            particle.radius = radius;
+            if read_u8(&mut file)? != 0 {
+                particle.kind = ParticleKind::Test;
+            }
+            particles.push(particle);
+        }
+        let count = read_u64(&mut file)?;
+        check_length(&mut file, file_length, count.saturating_mul(24))?;
+        for _ in 0..count {
+            frames.push((read_u64(&mut file)?, read_f64(&mut file)?));
+            let offset = read_u64(&mut file)?;
+            if offset < frames_start || offset > file_length.saturating_sub(20) {
+                return Err(invalid("a frame lies outside the file"));
+            }
+            offsets.push(offset);
+        }
+    } else {
+        // A recording that was never finished has no index, so its complete frames are found one by one
+        let mut offset = frames_start;
+        while offset + 20 <= file_length {
+            file.seek(SeekFrom::Start(offset))?;
+            let step = read_u64(&mut file)?;
+            let time = read_f64(&mut file)?;
+            let end = offset + 20 + 40 * read_u32(&mut file)? as u64;
+            if end > file_length {
+                break;
+            }
+            frames.push((step, time));
+            offsets.push(offset);
+            offset = end;
+        }
+    }
+
+    let mut model = Model::new(Vec::new(), delta_t, scale_factor, 0.0, do_collisions, 1.0, name);
+    model.point_size = point_size;
+    model.units = units;
+    model.epoch = (!epoch.is_nan()).then_some(epoch);
+    Ok(Recording { model, frames, current: 0, particles, source: FrameSource::Binary { file, file_length, offsets } })
+}
+
+// CSV recordings are read whole; lengths in meters or AU are converted back to SI, other units are shown as written
+fn open_csv(path: &Path) -> io::Result<Recording> {
+    let invalid = |line: usize, reason: &str| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, reason));
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let columns = split_csv(&header);
+    if columns.len() != 9 || columns[0] != "step" {
+        return Err(invalid(1, "not a trajectory recording"));
+    }
+    let units = match columns[4].as_str() {
+        "x (m)" => UnitSystem::Si,
+        "x (AU)" => UnitSystem::Astronomical,
+        _ => UnitSystem::Model,
+    };
+    let quantities = [Quantity::Length, Quantity::Length, Quantity::Velocity, Quantity::Velocity, Quantity::Mass];
+
+    let mut particles: Vec<Particle> = Vec::new();
+    let mut frames = Vec::new();
+    let mut states: Vec<Vec<[f64; 5]>> = Vec::new();
+    for (number, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv(&line);
+        if fields.len() != 9 {
+            return Err(invalid(number + 2, "expected 9 columns"));
+        }
+        let number_at = |column: usize| fields[column].parse::<f64>().map_err(|_| invalid(number + 2, "expected a number"));
+        let step = fields[0].parse::<u64>().map_err(|_| invalid(number + 2, "expected a step count"))?;
+        let time = units.model_value(number_at(1)?, Quantity::Time);
+        let id = fields[2].parse::<usize>().map_err(|_| invalid(number + 2, "expected a particle index"))?;
+        if frames.last().is_none_or(|&(last, _)| last != step) {
+            frames.push((step, time));
+            states.push(Vec::new());
+        }
+        let mut state = [0.0; 5];
+        for (k, quantity) in quantities.iter().enumerate() {
+            state[k] = units.model_value(number_at(4 + k)?, *quantity);
+        }
+        let frame = states.last_mut().unwrap();
+        if id != frame.len() {
+            return Err(invalid(number + 2, "particles must be listed in order within a step"));
+        }
+        frame.push(state);
+        if id == particles.len() {
+            particles.push(Particle::new([0.0, 0.0], [0.0, 0.0], 1.0, 1.0, fields[3].clone(), false));
+        }
+    }
+
+    // The recording does not hold the time step, so it is taken from the first two frames
+    let delta_t = match frames.as_slice() {
+        [(first_step, first_time), (second_step, second_time), ..] if second_step > first_step => {
+            (second_time - first_time) / (second_step - first_step) as f64
+        }
+        _ => 1.0,
+    };
+    let extent = states
+        .first()
+        .into_iter()
+        .flatten()
+        .map(|state| state[0].abs().max(state[1].abs()))
+        .fold(0.0, f64::max);
+    let scale_factor = if extent > 0.0 { (300.0 / extent) as f32 } else { 1.0 };
+    let name = path.file_stem().map_or("Recording".into(), |stem| stem.to_string_lossy().into_owned());
+    let mut model = Model::new(Vec::new(), delta_t, scale_factor, 0.0, false, 1.0, name);
+    model.units = units;
+    Ok(Recording { model, frames, current: 0, particles, source: FrameSource::Csv { frames: states } })
+}
+
+// Splits a CSV line, honouring quoted fields
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut characters = line.chars().peekable();
+    while let Some(c) = characters.next() {
+        match c {
+            '"' if quoted && characters.peek() == Some(&'"') => {
+                characters.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+// Fails before allocating when a count or length read from a corrupt file claims more bytes than the file has left
+fn check_length(reader: &mut impl Seek, file_length: u64, needed: u64) -> io::Result<()> {
+    if needed > file_length.saturating_sub(reader.stream_position()?) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "a length runs past the end of the file"));
+    }
+    Ok(())
+}
+
+fn read_string(reader: &mut (impl Read + Seek), file_length: u64) -> io::Result<String> {
+    let length = read_u32(reader)?;
+    check_length(reader, file_length, length as u64)?;
+    let mut bytes = vec![0; length as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+// Plays a recording back in the window: Space plays and pauses, SHIFT+brackets change the speed, brackets zoom,
+// Left and Right step one frame, Home and End jump to either end, and the timeline can be dragged
+pub(crate) async fn replay(mut recording: Recording) {
+    let mut scale_factor = recording.model.scale_factor;
+    let scale_ref = scale_factor;
+    let mut playing = false;
+    let mut speed: f64 = 30.0;             // frames per second
+    let mut cursor: f64 = 0.0;             // fractional frame, advanced while playing
+    let mut scrubbing = false;
+    let mut error: Option<String> = None;
+    let last = recording.frames.len() - 1;
+
+    loop {
+        clear_background(BLACK);
+
+        if is_key_pressed(KeyCode::RightBracket) {
+            if is_key_down(KeyCode::LeftShift) {
+                speed *= 2.0;
+            } else {
+                scale_factor *= 11.0 / 10.0;
+            }
+        }
+        if is_key_pressed(KeyCode::LeftBracket) {
+            if is_key_down(KeyCode::LeftShift) {
+                speed /= 2.0;
+            } else {
+                scale_factor /= 11.0 / 10.0;
+            }
+        }
+        if is_key_pressed(KeyCode::Space) {
+            if recording.current == last {
+                cursor = 0.0;
+            }
+            playing = !playing;
+        }
+        if is_key_pressed(KeyCode::Right) {
+            cursor = (cursor.floor() + 1.0).min(last as f64);
+            playing = false;
+        }
+        if is_key_pressed(KeyCode::Left) {
+            cursor = (cursor.ceil() - 1.0).max(0.0);
+            playing = false;
+        }
+        if is_key_pressed(KeyCode::Home) {
+            cursor = 0.0;
+        }
+        if is_key_pressed(KeyCode::End) {
+            cursor = last as f64;
+        }
+
+        // Timeline scrubber along the bottom of the window
+        let timeline_top = screen_height() - TIMELINE_MARGIN - TIMELINE_HEIGHT;
+        let timeline_width = screen_width() - 2.0 * TIMELINE_MARGIN;
+        let (mouse_x, mouse_y) = mouse_position();
+        if is_mouse_button_pressed(MouseButton::Left) && (timeline_top - 8.0..timeline_top + TIMELINE_HEIGHT + 8.0).contains(&mouse_y) {
+            scrubbing = true;
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            scrubbing = false;
+        }
+        if scrubbing {
+            let fraction = ((mouse_x - TIMELINE_MARGIN) / timeline_width).clamp(0.0, 1.0);
+            cursor = (fraction as f64 * last as f64).round();
+            playing = false;
+        }
+
+        if playing {
+            cursor += speed * get_frame_time() as f64;
+            if cursor >= last as f64 {
+                cursor = last as f64;
+                playing = false;
+            }
+        }
+        let frame = cursor.floor() as usize;
+        if frame != recording.current {
+            if let Err(reason) = recording.show_frame(frame) {
+                error = Some(format!("Could not read frame {}: {}", frame, reason));
+                playing = false;
+            }
+        }
+
+        recording.model.draw(scale_factor);
+
+        let model = &recording.model;
+        draw_text(&format!("Replay: {}", model.name), 20.0, 20.0, 16.0, YELLOW);
+        draw_text(&format!("Frame {} of {} ({})", recording.current + 1, last + 1, if playing { "playing" } else { "paused" }), 20.0, 50.0, 16.0, if playing { YELLOW } else { RED });
+        draw_text(&format!("Elapsed: {} ({} steps)", elapsed_text(model), model.steps), 20.0, 80.0, 16.0, YELLOW);
+        draw_text(&format!("Number of particles: {}", model.particles.len()), 20.0, 110.0, 16.0, YELLOW);
+        draw_text(&format!("Speed: {} frames per second", round_to_place(speed, 2)), 20.0, 140.0, 16.0, YELLOW);
+        if let Some(date) = current_julian_date(model) {
+            draw_text(&format!("Date: {} TDB (JD {:.4})", calendar_date(date), date), 20.0, 170.0, 16.0, YELLOW);
+        }
+        draw_text(&format!("Units: {}", model.units.name()), 20.0, screen_height() - 80.0, 16.0, RED);
+        draw_text(&format!("Zoom: {}%", round_to_place((scale_factor / scale_ref * 100.0).into(), 2)), 20.0, screen_height() - 60.0, 16.0, RED);
+        if let Some(message) = &error {
+            draw_text(message, 20.0, screen_height() - 100.0, 16.0, RED);
+        }
+
+        draw_rectangle(TIMELINE_MARGIN, timeline_top, timeline_width, TIMELINE_HEIGHT, DARKGRAY);
+        let progress = if last == 0 { 1.0 } else { recording.current as f32 / last as f32 };
+        draw_rectangle(TIMELINE_MARGIN, timeline_top, timeline_width * progress, TIMELINE_HEIGHT, GRAY);
+        draw_circle(TIMELINE_MARGIN + timeline_width * progress, timeline_top + TIMELINE_HEIGHT / 2.0, 7.0, YELLOW);
+
+        next_frame().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use super::*;
+    use crate::models::solar_system;
+    use crate::recorder::Recorder;
+
+    fn temporary_path(name: &str, format: RecordFormat) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.{}", name, std::process::id(), format.extension()))
+    }
+
+    // Records every 10th of 50 steps, and keeps the model as it was at each recorded frame
+    fn record(path: &Path, format: RecordFormat) -> (Recorder, Vec<Model>) {
+        let mut model = solar_system();
+        let mut recorder = Recorder::create(path, format, 10, &model).unwrap();
+        let mut expected = vec![model.clone()];
+        for _ in 0..50 {
+            model.step();
+            recorder.record(&model).unwrap();
+            if model.steps.is_multiple_of(10) {
+                expected.push(model.clone());
+            }
+        }
+        (recorder, expected)
+    }
+
+    fn assert_frames(recording: &mut Recording, expected: &[Model], tolerance: f64) {
+        assert_eq!(recording.frames.len(), expected.len());
+        for (index, model) in expected.iter().enumerate() {
+            recording.show_frame(index).unwrap();
+            assert_eq!(recording.model.steps, model.steps);
+            assert!((recording.model.elapsed_time - model.elapsed_time).abs() <= tolerance * model.elapsed_time);
+            assert_eq!(recording.model.particles.len(), model.particles.len());
+            for (replayed, particle) in recording.model.particles.iter().zip(&model.particles) {
+                for k in 0..2 {
+                    let scale = particle.position[k].abs().max(1.0);
+                    assert!((replayed.position[k] - particle.position[k]).abs() <= tolerance * scale, "{} position", particle.name);
+                }
+                assert!((replayed.mass - particle.mass).abs() <= tolerance * particle.mass, "{} mass", particle.name);
+            }
+        }
+    }
+
+    #[test]
+    fn finished_binary_recording_replays_exactly() {
+        let path = temporary_path("finished", RecordFormat::Binary);
+        let (recorder, expected) = record(&path, RecordFormat::Binary);
+        recorder.finish(expected.last().unwrap()).unwrap();
+        let recording = Recording::open(&path);
+        fs::remove_file(&path).unwrap();
+        let mut recording = recording.unwrap();
+        assert_eq!(recording.model.name, expected[0].name);
+        assert_eq!(recording.model.delta_t, expected[0].delta_t);
+        let names = |particles: &[Particle]| particles.iter().map(|particle| particle.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&recording.model.particles), names(&expected[0].particles));
+        assert_frames(&mut recording, &expected, 0.0);
+    }
+
+    #[test]
+    fn unfinished_binary_recording_replays_its_complete_frames() {
+        let path = temporary_path("unfinished", RecordFormat::Binary);
+        let (recorder, expected) = record(&path, RecordFormat::Binary);
+        // Dropping the recorder flushes the frames written so far but never writes the index
+        drop(recorder);
+        let recording = Recording::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert_frames(&mut recording.unwrap(), &expected, 0.0);
+    }
+
+    #[test]
+    fn csv_recording_replays_in_si_units() {
+        let path = temporary_path("trajectory", RecordFormat::Csv);
+        let (recorder, expected) = record(&path, RecordFormat::Csv);
+        recorder.finish(expected.last().unwrap()).unwrap();
+        let recording = Recording::open(&path);
+        fs::remove_file(&path).unwrap();
+        // Values pass through AU and decimal text, so they come back to within rounding
+        assert_frames(&mut recording.unwrap(), &expected, 1e-12);
+    }
+
+    #[test]
+    fn corrupt_lengths_are_rejected_without_allocating() {
+        let path = temporary_path("corrupt", RecordFormat::Binary);
+        let (recorder, expected) = record(&path, RecordFormat::Binary);
+        recorder.finish(expected.last().unwrap()).unwrap();
+        let original = fs::read(&path).unwrap();
+
+        // The model name's length, just after the magic and version
+        let mut bytes = original.clone();
+        bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        let name = Recording::open(&path).err();
+
+        // The particle count of the table, whose offset is written just before the index magic
+        let mut bytes = original;
+        let table = u64::from_le_bytes(bytes[bytes.len() - 16..bytes.len() - 8].try_into().unwrap()) as usize;
+        bytes[table..table + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        let table = Recording::open(&path).err();
+
+        fs::remove_file(&path).unwrap();
+        assert!(name.is_some_and(|error| error.contains("past the end of the file")));
+        assert!(table.is_some_and(|error| error.contains("past the end of the file")));
+    }
+}
@@ -0,0 +1,261 @@
+use macroquad::color::{Color, GRAY, RED, YELLOW};
+use macroquad::prelude::{draw_line, draw_rectangle, draw_text, screen_width};
+use serde::{Deserialize, Serialize};
+use crate::structs::{Model, Particle, ParticleKind};
+
+pub(crate) const STANDARD_GRAVITY: f64 = 9.80665;   // m/s^2, converts specific impulse to exhaust velocity
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ThrustDirection {
+    Prograde,               // along the current velocity
+    Retrograde,             // against the current velocity
+    Fixed(f64),             // radians from the +x axis
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum Manoeuvre {
+    Impulse {
+        time: f64,                          // seconds
+        delta_v: f64,                       // m/s
+        direction: ThrustDirection,
+        specific_impulse: Option<f64>,      // seconds; no propellant is used if unset
+    },
+    Burn {
+        start: f64,                         // seconds
+        duration: f64,                      // seconds
+        thrust: f64,                        // newtons
+        specific_impulse: f64,              // seconds
+        direction: ThrustDirection,
+    },
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub(crate) struct Spacecraft {
+    pub(crate) dry_mass: f64,               // kilograms, the particle mass never drops below this
+    pub(crate) schedule: Vec<Manoeuvre>,
+    pub(crate) executed: Vec<bool>,         // impulses already applied, parallel to schedule
+}
+
+impl ThrustDirection {
+    fn unit_vector(&self, velocity: [f64; 2]) -> [f64; 2] {
+        let speed = (velocity[0].powi(2) + velocity[1].powi(2)).sqrt();
+        match self {
+            ThrustDirection::Prograde if speed > 0.0 => [velocity[0] / speed, velocity[1] / speed],
+            ThrustDirection::Retrograde if speed > 0.0 => [-velocity[0] / speed, -velocity[1] / speed],
+            ThrustDirection::Fixed(angle) => [angle.cos(), angle.sin()],
+            _ => [0.0, 0.0],
+        }
+    }
+}
+
+impl Manoeuvre {
+    pub(crate) fn end(&self) -> f64 {
+        match self {
+            Manoeuvre::Impulse { time, .. } => *time,
+            Manoeuvre::Burn { start, duration, .. } => start + duration,
+        }
+    }
+}
+
+impl Spacecraft {
+    pub(crate) fn new(dry_mass: f64, schedule: Vec<Manoeuvre>) -> Self {
+        let executed = vec![false; schedule.len()];
+        Spacecraft {
+            dry_mass,
+            schedule,
+            executed,
+        }
+    }
+}
+
+// Checks the values serde cannot, e.g. negative thrust or a time that is not a number
+pub(crate) fn validate_schedule(schedule: &[Manoeuvre]) -> Result<(), String> {
+    for (i, manoeuvre) in schedule.iter().enumerate() {
+        let direction = match manoeuvre {
+            Manoeuvre::Impulse { direction, .. } | Manoeuvre::Burn { direction, .. } => direction,
+        };
+        if let ThrustDirection::Fixed(angle) = direction {
+            if !angle.is_finite() {
+                return Err(format!("manoeuvre {}: the direction angle must be finite", i + 1));
+            }
+        }
+        match manoeuvre {
+            Manoeuvre::Impulse { time, delta_v, specific_impulse, .. } => {
+                if !time.is_finite() || !delta_v.is_finite() {
+                    return Err(format!("manoeuvre {}: time and delta_v must be finite", i + 1));
+                }
+                if *delta_v < 0.0 {
+                    return Err(format!("manoeuvre {}: delta_v must not be negative", i + 1));
+                }
+                if specific_impulse.is_some_and(|isp| !isp.is_finite() || isp <= 0.0) {
+                    return Err(format!("manoeuvre {}: specific_impulse must be positive", i + 1));
+                }
+            }
+            Manoeuvre::Burn { start, duration, thrust, specific_impulse, .. } => {
+                if [*start, *duration, *thrust, *specific_impulse].iter().any(|value| !value.is_finite()) {
+                    return Err(format!("manoeuvre {}: start, duration, thrust and specific_impulse must be finite", i + 1));
+                }
+                if *duration <= 0.0 || *thrust < 0.0 || *specific_impulse <= 0.0 {
+                    return Err(format!("manoeuvre {}: burns need a positive duration and specific_impulse and a non-negative thrust", i + 1));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Finite burns that fire during the step starting at the given time, with the propellant each uses. Burns are
+// taken in schedule order from one running mass, and a burn that would take the mass below the dry mass does not fire
+fn active_burns(spacecraft: &Spacecraft, mass: f64, time: f64, delta_t: f64) -> Vec<(usize, f64)> {
+    let mut mass = mass;
+    let mut burns = Vec::new();
+    for (i, manoeuvre) in spacecraft.schedule.iter().enumerate() {
+        if let Manoeuvre::Burn { start, duration, thrust, specific_impulse, .. } = manoeuvre {
+            let propellant_used = thrust / (specific_impulse * STANDARD_GRAVITY) * delta_t;
+            if time >= *start && time < start + duration && mass - propellant_used >= spacecraft.dry_mass {
+                mass -= propellant_used;
+                burns.push((i, propellant_used));
+            }
+        }
+    }
+    burns
+}
+
+// Thrust of any finite burns active at the given time, in newtons
+pub(crate) fn thrust_force(particle: &Particle, time: f64, delta_t: f64) -> [f64; 2] {
+    let ParticleKind::Spacecraft(spacecraft) = &particle.kind else {
+        return [0.0, 0.0];
+    };
+    let mut force = [0.0, 0.0];
+    for (i, _) in active_burns(spacecraft, particle.mass, time, delta_t) {
+        if let Manoeuvre::Burn { thrust, direction, .. } = &spacecraft.schedule[i] {
+            let unit = direction.unit_vector(particle.velocity(delta_t));
+            force[0] += thrust * unit[0];
+            force[1] += thrust * unit[1];
+        }
+    }
+    force
+}
+
+// Burns the propellant of the finite burns that thrust_force applied this step, then applies impulses that fall
+// within the step; called after the particle is integrated
+pub(crate) fn fire_thrusters(particle: &mut Particle, time: f64, delta_t: f64) {
+    let velocity = particle.velocity(delta_t);
+    let ParticleKind::Spacecraft(spacecraft) = &mut particle.kind else {
+        return;
+    };
+
+    let mut mass = particle.mass;
+    for (_, propellant_used) in active_burns(spacecraft, mass, time, delta_t) {
+        mass -= propellant_used;
+    }
+    let mut velocity_change = [0.0, 0.0];
+    for (i, manoeuvre) in spacecraft.schedule.iter().enumerate() {
+        if let Manoeuvre::Impulse { time: burn_time, delta_v, direction, specific_impulse } = manoeuvre {
+            if spacecraft.executed[i] || *burn_time >= time + delta_t {
+                continue;
+            }
+            spacecraft.executed[i] = true;
+
+            // Tsiolkovsky: the delta-v is limited by the propellant left on board
+            let mut applied_delta_v = *delta_v;
+            if let Some(isp) = specific_impulse {
+                let exhaust_velocity = isp * STANDARD_GRAVITY;
+                applied_delta_v = applied_delta_v.min(exhaust_velocity * (mass / spacecraft.dry_mass).ln());
+                mass /= (applied_delta_v / exhaust_velocity).exp();
+            }
+            let unit = direction.unit_vector(velocity);
+            velocity_change[0] += applied_delta_v * unit[0];
+            velocity_change[1] += applied_delta_v * unit[1];
+        }
+    }
+
+    particle.mass = mass;
+    particle.previous_position[0] -= velocity_change[0] * delta_t;
+    particle.previous_position[1] -= velocity_change[1] * delta_t;
+}
+
+// Manoeuvre timeline for every spacecraft in the model, drawn along the top right of the screen
+pub(crate) fn draw_timeline(model: &Model) {
+    let left = screen_width() - 340.0;
+    let width = 320.0;
+    let mut y = 30.0;
+
+    for particle in model.particles.iter() {
+        let ParticleKind::Spacecraft(spacecraft) = &particle.kind else {
+            continue;
+        };
+        let span = spacecraft.schedule.iter().map(|manoeuvre| manoeuvre.end()).fold(model.elapsed_time, f64::max) * 1.1;
+        let to_screen = |time: f64| left + (time / span.max(f64::MIN_POSITIVE)) as f32 * width;
+
+        draw_text(&format!("{} ({:.1} kg)", particle.name, particle.mass), left, y - 8.0, 16.0, YELLOW);
+        draw_line(left, y, left + width, y, 2.0, GRAY);
+        for (i, manoeuvre) in spacecraft.schedule.iter().enumerate() {
+            let done = spacecraft.executed[i] || manoeuvre.end() <= model.elapsed_time;
+            let color = if done { GRAY } else { Color::new(0.00, 0.89, 0.19, 1.00) };
+            match manoeuvre {
+                Manoeuvre::Impulse { time, .. } => {
+                    draw_line(to_screen(*time), y - 6.0, to_screen(*time), y + 6.0, 2.0, color);
+                }
+                Manoeuvre::Burn { start, duration, .. } => {
+                    let x = to_screen(*start);
+                    draw_rectangle(x, y - 4.0, (to_screen(start + duration) - x).max(2.0), 8.0, color);
+                }
+            }
+        }
+        let cursor = to_screen(model.elapsed_time);
+        draw_line(cursor, y - 8.0, cursor, y + 8.0, 2.0, RED);
+        y += 40.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn burn(start: f64, thrust: f64) -> Manoeuvre {
+        Manoeuvre::Burn { start, duration: 10.0, thrust, specific_impulse: 300.0, direction: ThrustDirection::Fixed(0.0) }
+    }
+
+    #[test]
+    fn overlapping_burns_share_one_running_mass() {
+        let delta_t = 1.0;
+        let thrust = 1000.0;
+        let propellant_per_step = thrust / (300.0 * STANDARD_GRAVITY) * delta_t;
+        // Enough propellant for one and a half burn steps, so only the first of two overlapping burns can fire
+        let dry_mass = 100.0;
+        let mut particle = Particle::new([0.0, 0.0], [1.0, 0.0], dry_mass + 1.5 * propellant_per_step, delta_t, "Probe".into(), false);
+        particle.kind = ParticleKind::Spacecraft(Spacecraft::new(dry_mass, vec![burn(0.0, thrust), burn(0.0, thrust)]));
+
+        let force = thrust_force(&particle, 0.0, delta_t);
+        fire_thrusters(&mut particle, 0.0, delta_t);
+        assert_eq!(force, [thrust, 0.0]);
+        assert!((particle.mass - (dry_mass + 0.5 * propellant_per_step)).abs() < 1e-12);
+
+        // The next step has too little propellant left for either
+        assert_eq!(thrust_force(&particle, delta_t, delta_t), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn non_finite_values_are_rejected() {
+        let impulse = |time: f64, delta_v: f64, angle: f64| Manoeuvre::Impulse {
+            time,
+            delta_v,
+            direction: ThrustDirection::Fixed(angle),
+            specific_impulse: None,
+        };
+        assert!(validate_schedule(&[impulse(0.0, 1.0, 0.0), burn(5.0, 10.0)]).is_ok());
+        assert!(validate_schedule(&[impulse(f64::NAN, 1.0, 0.0)]).is_err());
+        assert!(validate_schedule(&[impulse(0.0, f64::NAN, 0.0)]).is_err());
+        assert!(validate_schedule(&[impulse(0.0, 1.0, f64::INFINITY)]).is_err());
+        assert!(validate_schedule(&[burn(f64::NAN, 10.0)]).is_err());
+        assert!(validate_schedule(&[burn(0.0, f64::INFINITY)]).is_err());
+        let mut nan_duration = burn(0.0, 10.0);
+        if let Manoeuvre::Burn { duration, .. } = &mut nan_duration {
+            *duration = f64::NAN;
+        }
+        assert!(validate_schedule(&[nan_duration]).is_err());
+    }
+}
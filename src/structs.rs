@@ -1,8 +1,28 @@
 use std::f32::consts::PI;
-use macroquad::color::{Color, WHITE};
-use macroquad::prelude::{draw_circle, draw_text, screen_height, screen_width};
+use macroquad::color::{Color, GRAY, WHITE};
+use macroquad::prelude::{draw_circle, draw_line, draw_text, screen_height, screen_width};
+use serde::{Deserialize, Serialize};
+use crate::checkpoint::SimulationRng;
+use crate::constraints::Constraint;
+use crate::fields::ForceField;
+use crate::frames::{fictitious_force, rotate, ReferenceFrame};
+use crate::interactions::*;
+use crate::motion::{KinematicPath, Motion};
+use crate::orbits::{orbit_from_state, Orbit, OrbitalElements};
+use crate::spacecraft::{fire_thrusters, thrust_force, Spacecraft};
+use crate::units::{default_units, UnitSystem};
+use crate::util::*;
 
-#[derive(Clone)]
+const CONSTRAINT_ITERATIONS: usize = 10;   // relaxation passes over the rods each step
+
+#[derive(Clone, Deserialize, Serialize)]
+pub(crate) enum ParticleKind {
+    Body,
+    Spacecraft(Spacecraft),
+    Test,                   // feels the other particles but exerts no force and never collides
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub(crate) struct Particle {
     pub(crate) position: [f64; 2],            // meters
     pub(crate) previous_position: [f64; 2],   // meters
@@ -10,19 +30,43 @@ pub(crate) struct Particle {
     pub(crate) acceleration: [f64; 2],        // m/s^2
     pub(crate) force: [f64; 2],               // newtons
     pub(crate) mass: f64,                     // kilograms
+    pub(crate) charge: f64,                   // coulombs
     pub(crate) name: String,
+    pub(crate) kind: ParticleKind,
+    pub(crate) motion: Motion,
 }
 
+#[derive(Clone, Deserialize, Serialize)]
 pub(crate) struct Model {
     pub(crate) particles: Vec<Particle>,            // predefined particles
     pub(crate) default_particles: Vec<Particle>,    // default particles; auto-set
     pub(crate) delta_t: f64,                        // time step
     pub(crate) default_delta_t: f64,                // default time step; auto set
+    pub(crate) substeps: usize,                     // time steps integrated per drawn frame
     pub(crate) scale_factor: f32,                   // scale factor
     pub(crate) g_constant: f64,                     // G constant
     pub(crate) do_collisions: bool,                 // should collisions be calculated ?
     pub(crate) restitution: f64,                    // restitution value for collisions
     pub(crate) name: String,                        // Model name
+    pub(crate) elapsed_time: f64,                   // simulated seconds since the model started
+    pub(crate) default_elapsed_time: f64,           // time of the default particles, non-zero for saves; restored on reset
+    pub(crate) steps: u64,                          // time steps integrated since the model started
+    pub(crate) default_steps: u64,                  // steps of the default particles; restored on reset
+    pub(crate) force_law: ForceLawKind,             // pairwise force between particles
+    pub(crate) force_fields: Vec<ForceField>,       // external force fields
+    pub(crate) constraints: Vec<Constraint>,        // springs and rods between particles
+    pub(crate) default_constraints: Vec<Constraint>,// default constraints; restored on reset
+    pub(crate) relativistic: bool,                  // apply 1PN correction around the dominant mass ?
+    pub(crate) speed_of_light: f64,                 // c, used by the 1PN correction
+    pub(crate) frame: ReferenceFrame,               // frame the model is integrated and drawn in
+    pub(crate) default_frame: ReferenceFrame,       // frame of the default particles; restored on reset
+    pub(crate) angular_velocity: f64,               // rad/s, rotation rate of the co-rotating frames
+    pub(crate) default_angular_velocity: f64,       // rotation rate of the default frame; restored on reset
+    pub(crate) epoch: Option<f64>,                  // Julian date (TDB) at zero elapsed time, if known
+    pub(crate) point_size: f32,                     // pixels, radius particles are drawn with when collisions are off
+    pub(crate) units: UnitSystem,                   // units values are shown, saved and exported in
+    pub(crate) rng: SimulationRng,                  // random numbers drawn while the model runs, checkpointed with it
+    pub(crate) seed: Option<u64>,                   // seed of the random numbers, 0 if unset; restored on reset
 }
 
 impl Particle {
@@ -32,6 +76,7 @@ impl Particle {
         previous_position[1] -= initial_velocity[1] * time_step;
 
         let radius = if do_collisions {
+            #[allow(clippy::approx_constant)]
             let density = 0.318;
             let area = mass / density;
             (area / PI as f64).sqrt()
@@ -46,7 +91,43 @@ impl Particle {
             acceleration: [0.0, 0.0],
             force: [0.0, 0.0],
             mass,
+            charge: 0.0,
+            name,
+            kind: ParticleKind::Body,
+            motion: Motion::Dynamic,
+        }
+    }
+
+    // Places a particle on an orbit about a central particle, moving with it
+    pub(crate) fn from_orbital_elements(central: &Particle, elements: OrbitalElements, mass: f64, g_constant: f64, time_step: f64, name: String, do_collisions: bool) -> Self {
+        let (relative_position, relative_velocity) = elements.state(g_constant * (central.mass + mass));
+        let central_velocity = central.velocity(time_step);
+        Particle::new(
+            [central.position[0] + relative_position[0], central.position[1] + relative_position[1]],
+            [central_velocity[0] + relative_velocity[0], central_velocity[1] + relative_velocity[1]],
+            mass,
+            time_step,
             name,
+            do_collisions,
+        )
+    }
+
+    pub(crate) fn velocity(&self, delta_t: f64) -> [f64; 2] {
+        [
+            (self.position[0] - self.previous_position[0]) / delta_t,
+            (self.position[1] - self.previous_position[1]) / delta_t,
+        ]
+    }
+
+    pub(crate) fn is_test(&self) -> bool {
+        matches!(self.kind, ParticleKind::Test)
+    }
+
+    // Zero for pinned and kinematic particles, which no collision or constraint can move
+    pub(crate) fn inverse_mass(&self) -> f64 {
+        match self.motion {
+            Motion::Dynamic => 1.0 / self.mass,
+            Motion::Pinned | Motion::Kinematic(_) => 0.0,
         }
     }
 
@@ -68,59 +149,482 @@ impl Model{
         Model {
             particles: defaults.clone(),
             default_particles: defaults,
-            delta_t: delta_t.clone(),
+            delta_t,
             default_delta_t: delta_t,
+            substeps: 1,
             scale_factor,
             g_constant,
             do_collisions,
             restitution,
-            name
+            name,
+            elapsed_time: 0.0,
+            default_elapsed_time: 0.0,
+            steps: 0,
+            default_steps: 0,
+            force_law: ForceLawKind::Newtonian(Newtonian { softening: 0.0 }),
+            force_fields: Vec::new(),
+            constraints: Vec::new(),
+            default_constraints: Vec::new(),
+            relativistic: false,
+            speed_of_light: 299_792_458.0,
+            frame: ReferenceFrame::Inertial,
+            default_frame: ReferenceFrame::Inertial,
+            angular_velocity: 0.0,
+            default_angular_velocity: 0.0,
+            epoch: None,
+            point_size: 5.0,
+            units: default_units(g_constant),
+            rng: SimulationRng::new(0),
+            seed: None,
         }
     }
 
     pub(crate) fn reset(&mut self) {
         self.particles = self.default_particles.clone();
-        self.delta_t = self.default_delta_t.clone();
+        self.delta_t = self.default_delta_t;
+        self.elapsed_time = self.default_elapsed_time;
+        self.steps = self.default_steps;
+        self.constraints = self.default_constraints.clone();
+        self.frame = self.default_frame;
+        self.angular_velocity = self.default_angular_velocity;
+        self.rng = SimulationRng::new(self.seed.unwrap_or(0));
+    }
+
+    // Starts the random numbers drawn while the model runs over from a seed
+    pub(crate) fn reseed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.rng = SimulationRng::new(seed);
+    }
+
+    // Moves the origin to the centre of mass and removes the net momentum of the current state
+    pub(crate) fn recentre_to_barycentre(&mut self) {
+        let total_mass: f64 = self.particles.iter().map(|particle| particle.mass).sum();
+        if self.particles.is_empty() || total_mass == 0.0 {
+            return;
+        }
+        let mut center_of_mass = [0.0, 0.0];
+        for particle in self.particles.iter() {
+            center_of_mass[0] += particle.position[0] * particle.mass / total_mass;
+            center_of_mass[1] += particle.position[1] * particle.mass / total_mass;
+        }
+        let momentum = calculate_linear_momentum(&self.particles, self.delta_t);
+        let velocity_shift = [
+            momentum[0] / total_mass * self.delta_t,
+            momentum[1] / total_mass * self.delta_t,
+        ];
+
+        for particle in self.particles.iter_mut() {
+            particle.position[0] -= center_of_mass[0];
+            particle.position[1] -= center_of_mass[1];
+            particle.previous_position[0] -= center_of_mass[0] - velocity_shift[0];
+            particle.previous_position[1] -= center_of_mass[1] - velocity_shift[1];
+        }
+    }
+
+    // Recentres a model as it is built or loaded, so that resetting it also starts from the barycentre
+    pub(crate) fn start_at_barycentre(&mut self) {
+        self.recentre_to_barycentre();
+        self.default_particles = self.particles.clone();
+    }
+
+    // Rotation rate of the two most massive bodies about each other, as for a restricted three-body frame
+    pub(crate) fn binary_angular_velocity(&self) -> f64 {
+        let mut by_mass: Vec<usize> = (0..self.particles.len()).collect();
+        by_mass.sort_by(|&a, &b| self.particles[b].mass.total_cmp(&self.particles[a].mass));
+        let [first, second] = match by_mass[..] {
+            [first, second, ..] => [&self.particles[first], &self.particles[second]],
+            _ => return 0.0,
+        };
+
+        let separation = [
+            second.position[0] - first.position[0],
+            second.position[1] - first.position[1],
+        ];
+        let first_velocity = first.velocity(self.delta_t);
+        let second_velocity = second.velocity(self.delta_t);
+        let relative_velocity = [
+            second_velocity[0] - first_velocity[0],
+            second_velocity[1] - first_velocity[1],
+        ];
+        let distance = distance(first.position, second.position);
+        if distance == 0.0 {
+            return 0.0;
+        }
+        let angular_momentum = separation[0] * relative_velocity[1] - separation[1] * relative_velocity[0];
+        (self.g_constant * (first.mass + second.mass) / distance.powi(3)).sqrt() * angular_momentum.signum()
+    }
+
+    // Angle the co-rotating frame has turned through since the start of the run
+    pub(crate) fn frame_angle(&self) -> f64 {
+        self.angular_velocity * self.elapsed_time
+    }
+
+    // Where a particle is drawn; the co-rotating view turns inertial positions into the rotating frame
+    pub(crate) fn display_position(&self, index: usize) -> [f64; 2] {
+        match self.frame {
+            ReferenceFrame::CoRotatingView => rotate(self.particles[index].position, -self.frame_angle()),
+            _ => self.particles[index].position,
+        }
+    }
+
+    // Inverse of display_position for points picked on screen
+    pub(crate) fn world_position(&self, display: [f64; 2]) -> [f64; 2] {
+        match self.frame {
+            ReferenceFrame::CoRotatingView => rotate(display, self.frame_angle()),
+            _ => display,
+        }
+    }
+
+    // Switches frames, converting the state when moving in or out of co-rotating integration
+    pub(crate) fn set_frame(&mut self, frame: ReferenceFrame, angular_velocity: f64) {
+        let to_rotating = frame == ReferenceFrame::CoRotating && self.frame != ReferenceFrame::CoRotating;
+        let from_rotating = frame != ReferenceFrame::CoRotating && self.frame == ReferenceFrame::CoRotating;
+        if !from_rotating {
+            self.angular_velocity = angular_velocity;
+        }
+        let angle = self.frame_angle();
+        let omega = self.angular_velocity;
+        let delta_t = self.delta_t;
+
+        for particle in self.particles.iter_mut() {
+            let position = particle.position;
+            let velocity = particle.velocity(delta_t);
+            let (new_position, new_velocity) = if to_rotating {
+                let relative_velocity = [velocity[0] + omega * position[1], velocity[1] - omega * position[0]];
+                (rotate(position, -angle), rotate(relative_velocity, -angle))
+            } else if from_rotating {
+                let inertial_position = rotate(position, angle);
+                let rotated_velocity = rotate(velocity, angle);
+                (inertial_position, [rotated_velocity[0] - omega * inertial_position[1], rotated_velocity[1] + omega * inertial_position[0]])
+            } else {
+                continue;
+            };
+            particle.position = new_position;
+            particle.previous_position = [
+                new_position[0] - new_velocity[0] * delta_t,
+                new_position[1] - new_velocity[1] * delta_t,
+            ];
+        }
+        self.frame = frame;
+        if from_rotating {
+            self.angular_velocity = angular_velocity;
+        }
+    }
+
+    // Particle under a point in world space, within its radius or the given tolerance
+    pub(crate) fn particle_at(&self, position: [f64; 2], tolerance: f64) -> Option<usize> {
+        (0..self.particles.len())
+            .filter(|&i| distance(self.display_position(i), position) <= self.particles[i].radius.max(tolerance))
+            .min_by(|&a, &b| {
+                distance(self.display_position(a), position).total_cmp(&distance(self.display_position(b), position))
+            })
+    }
+
+    pub(crate) fn dominant_particle(&self) -> Option<usize> {
+        (0..self.particles.len()).max_by(|&a, &b| self.particles[a].mass.total_cmp(&self.particles[b].mass))
+    }
+
+    // Heaviest particle other than the given one, the default primary for its orbit
+    pub(crate) fn primary_of(&self, index: usize) -> Option<usize> {
+        (0..self.particles.len())
+            .filter(|&i| i != index)
+            .max_by(|&a, &b| self.particles[a].mass.total_cmp(&self.particles[b].mass))
+    }
+
+    // Osculating orbit of a particle about a primary, using inertial velocities
+    pub(crate) fn orbit_about(&self, index: usize, primary: usize) -> Option<Orbit> {
+        let body = &self.particles[index];
+        let center = &self.particles[primary];
+        let position = [body.position[0] - center.position[0], body.position[1] - center.position[1]];
+        if index == primary || (position[0] == 0.0 && position[1] == 0.0) {
+            return None;
+        }
+        let body_velocity = body.velocity(self.delta_t);
+        let center_velocity = center.velocity(self.delta_t);
+        let mut velocity = [body_velocity[0] - center_velocity[0], body_velocity[1] - center_velocity[1]];
+        if self.frame == ReferenceFrame::CoRotating {
+            velocity[0] -= self.angular_velocity * position[1];
+            velocity[1] += self.angular_velocity * position[0];
+        }
+        Some(orbit_from_state(self.g_constant * (body.mass + center.mass), position, velocity))
+    }
+
+    pub(crate) fn toggle_pinned(&mut self, index: usize) {
+        let particle = &mut self.particles[index];
+        particle.motion = match particle.motion {
+            Motion::Pinned => Motion::Dynamic,
+            _ => Motion::Pinned,
+        };
+        particle.previous_position = particle.position;
+    }
+
+    // Drives the particle on a circle about the current position of its primary (the heaviest other particle, or
+    // the origin if it is alone), at its current distance and angular speed about that point
+    pub(crate) fn toggle_kinematic(&mut self, index: usize) {
+        let delta_t = self.delta_t;
+        let time = self.elapsed_time;
+        let (center, center_velocity) = match self.primary_of(index) {
+            Some(primary) => (self.particles[primary].position, self.particles[primary].velocity(delta_t)),
+            None => ([0.0, 0.0], [0.0, 0.0]),
+        };
+        let particle = &mut self.particles[index];
+        if let Motion::Kinematic(_) = particle.motion {
+            particle.motion = Motion::Dynamic;
+            return;
+        }
+
+        let offset = [particle.position[0] - center[0], particle.position[1] - center[1]];
+        let radius = (offset[0].powi(2) + offset[1].powi(2)).sqrt();
+        if radius == 0.0 {
+            particle.motion = Motion::Pinned;
+            particle.previous_position = particle.position;
+            return;
+        }
+        let body_velocity = particle.velocity(delta_t);
+        let velocity = [body_velocity[0] - center_velocity[0], body_velocity[1] - center_velocity[1]];
+        // Only the tangential part of the velocity turns the particle about the centre
+        let tangential_speed = (offset[0] * velocity[1] - offset[1] * velocity[0]) / radius;
+        let period = if tangential_speed != 0.0 {
+            2.0 * std::f64::consts::PI * radius / tangential_speed
+        } else {
+            600.0 * delta_t
+        };
+        let angle = offset[1].atan2(offset[0]);
+        particle.motion = Motion::Kinematic(KinematicPath::Circular {
+            center,
+            radius,
+            period,
+            phase: angle - 2.0 * std::f64::consts::PI * time / period,
+        });
+    }
+
+    pub(crate) fn cycle_drag(&mut self, coefficient: f64) {
+        let next = match self.force_fields.iter().find(|field| matches!(field, ForceField::LinearDrag { .. } | ForceField::QuadraticDrag { .. })) {
+            None => Some(ForceField::LinearDrag { coefficient }),
+            Some(ForceField::LinearDrag { .. }) => Some(ForceField::QuadraticDrag { coefficient }),
+            Some(_) => None,
+        };
+        self.force_fields.retain(|field| !matches!(field, ForceField::LinearDrag { .. } | ForceField::QuadraticDrag { .. }));
+        if let Some(field) = next {
+            self.force_fields.push(field);
+        }
+    }
+
+    pub(crate) fn toggle_uniform_gravity(&mut self, acceleration: [f64; 2]) {
+        let count = self.force_fields.len();
+        self.force_fields.retain(|field| !matches!(field, ForceField::UniformGravity { .. }));
+        if self.force_fields.len() == count {
+            self.force_fields.push(ForceField::UniformGravity { acceleration });
+        }
     }
 
     pub(crate) fn change_speed(&mut self, delta_t: f64) {
-        let current_particles = &self.particles;
         let mut adjusted_particles: Vec<Particle> = Vec::new();
 
-        for i in 0..current_particles.len() {
-            let current_velocity = [
-                (current_particles[i].position[0] - current_particles[i].previous_position[0])/self.delta_t,
-                (current_particles[i].position[1] - current_particles[i].previous_position[1])/self.delta_t,
-            ];
+        for particle in self.particles.iter() {
+            let current_velocity = particle.velocity(self.delta_t);
 
-            let new_particle = Particle::new(
-                current_particles[i].position,
-                current_velocity,
-                current_particles[i].mass,
-                delta_t,
-                current_particles[i].name.clone(),
-                self.do_collisions,
-            );
+            let mut new_particle = particle.clone();
+            new_particle.previous_position[0] = particle.position[0] - current_velocity[0] * delta_t;
+            new_particle.previous_position[1] = particle.position[1] - current_velocity[1] * delta_t;
             adjusted_particles.push(new_particle);
         }
         self.particles = adjusted_particles;
         self.delta_t = delta_t;
     }
 
+    pub(crate) fn step(&mut self) {
+        // Phase 1: Resolve overlaps; test particles take no part in overlaps or pairwise forces
+        let sources: Vec<usize> = (0..self.particles.len()).filter(|&i| !self.particles[i].is_test()).collect();
+        for (k, &i) in sources.iter().enumerate() {
+            for &j in sources[k + 1..].iter() {
+                let distance = distance(
+                    self.particles[i].position,
+                    self.particles[j].position,
+                );
+
+                if distance < self.particles[i].radius + self.particles[j].radius {
+                    let overlap = self.particles[i].radius
+                        + self.particles[j].radius
+                        - distance;
+
+                    let total_inverse_mass = self.particles[i].inverse_mass() + self.particles[j].inverse_mass();
+                    if overlap > 0.0 && total_inverse_mass > 0.0 && distance > 0.0 {
+                        let mass_ratio_i = self.particles[i].inverse_mass() / total_inverse_mass;
+                        let mass_ratio_j = self.particles[j].inverse_mass() / total_inverse_mass;
+
+                        // Calculate separation vector
+                        let mut separation_vector = [
+                            self.particles[j].position[0] - self.particles[i].position[0],
+                            self.particles[j].position[1] - self.particles[i].position[1],
+                        ];
+
+                        let separation_magnitude = (separation_vector[0].powi(2) + separation_vector[1].powi(2)).sqrt();
+                        separation_vector[0] /= separation_magnitude;
+                        separation_vector[1] /= separation_magnitude;
+
+                        // Apply separation proportional to mass
+                        self.particles[i].position[0] -= separation_vector[0] * overlap * mass_ratio_i;
+                        self.particles[i].position[1] -= separation_vector[1] * overlap * mass_ratio_i;
+                        self.particles[j].position[0] += separation_vector[0] * overlap * mass_ratio_j;
+                        self.particles[j].position[1] += separation_vector[1] * overlap * mass_ratio_j;
+                    }
+
+                }
+            }
+        }
+
+        // Phase 2: Apply gravitational forces and resolve collisions
+        let dominant = if self.relativistic { self.dominant_particle() } else { None };
+        for i in 0..self.particles.len() {
+            self.particles[i].force = [0.0, 0.0];
+
+            for &j in sources.iter() {
+                if i != j {
+                    let distance = distance(
+                        self.particles[i].position,
+                        self.particles[j].position,
+                    );
+
+                    let mut g_components = self.force_law.force(
+                        self.g_constant,
+                        &self.particles[i],
+                        &self.particles[j],
+                    );
+
+                    // Prevent gravitational forces for overlapping particles
+                    if distance <= self.particles[i].radius + self.particles[j].radius {
+                        g_components = [0.0, 0.0];
+                    }
+
+                    self.particles[i].force[0] += g_components[0];
+                    self.particles[i].force[1] += g_components[1];
+
+                    // Handle collisions
+                    if !self.particles[i].is_test() && check_collision(
+                        &self.particles[i],
+                        &self.particles[j],
+                    ) {
+                        let impulse = resolve_collision(
+                            &self.particles[i],
+                            &self.particles[j],
+                            self.restitution,
+                            self.delta_t,
+                        );
+                        self.particles[i].previous_position[0] -= impulse[0];
+                        self.particles[i].previous_position[1] -= impulse[1];
+                        self.particles[j].previous_position[0] -= impulse[2];
+                        self.particles[j].previous_position[1] -= impulse[3];
+                    }
+                }
+            }
+
+            // External force fields
+            for field in self.force_fields.iter() {
+                let field_components = field.force(self.g_constant, &self.particles[i], self.delta_t);
+                self.particles[i].force[0] += field_components[0];
+                self.particles[i].force[1] += field_components[1];
+            }
+
+            // Springs
+            for constraint in self.constraints.iter() {
+                let (a, b) = constraint.particles();
+                if a == i || b == i {
+                    let spring_components = constraint.spring_force(&self.particles, self.delta_t);
+                    let sign = if a == i { 1.0 } else { -1.0 };
+                    self.particles[i].force[0] += sign * spring_components[0];
+                    self.particles[i].force[1] += sign * spring_components[1];
+                }
+            }
+
+            // Spacecraft thrust
+            let thrust_components = thrust_force(&self.particles[i], self.elapsed_time, self.delta_t);
+            self.particles[i].force[0] += thrust_components[0];
+            self.particles[i].force[1] += thrust_components[1];
+
+            // Coriolis and centrifugal forces in the rotating frame
+            if self.frame == ReferenceFrame::CoRotating {
+                let frame_components = fictitious_force(
+                    self.particles[i].mass,
+                    self.particles[i].position,
+                    self.particles[i].velocity(self.delta_t),
+                    self.angular_velocity,
+                );
+                self.particles[i].force[0] += frame_components[0];
+                self.particles[i].force[1] += frame_components[1];
+            }
+
+            // Relativistic correction for bodies orbiting the dominant mass
+            if let Some(primary) = dominant {
+                if primary != i {
+                    let pn_components = resolve_post_newtonian_force(
+                        self.g_constant,
+                        self.speed_of_light,
+                        &self.particles[primary],
+                        &self.particles[i],
+                        self.delta_t,
+                    );
+                    self.particles[i].force[0] += pn_components[0];
+                    self.particles[i].force[1] += pn_components[1];
+                }
+            }
+        }
+
+        // Phase 3: Update particles
+        for i in 0..self.particles.len() {
+            match &self.particles[i].motion {
+                Motion::Dynamic => {
+                    self.particles[i].update(self.delta_t);
+                    fire_thrusters(&mut self.particles[i], self.elapsed_time, self.delta_t);
+                }
+                Motion::Pinned => {
+                    self.particles[i].previous_position = self.particles[i].position;
+                }
+                Motion::Kinematic(path) => {
+                    let new_position = path.position_at(self.elapsed_time + self.delta_t);
+                    self.particles[i].previous_position = self.particles[i].position;
+                    self.particles[i].position = new_position;
+                }
+            }
+        }
+
+        // Phase 4: Hold rods at their length
+        for _ in 0..CONSTRAINT_ITERATIONS {
+            for constraint in self.constraints.iter() {
+                constraint.enforce(&mut self.particles);
+            }
+        }
+        self.elapsed_time += self.delta_t;
+        self.steps += 1;
+    }
+
     pub(crate) fn draw(&mut self, scale_factor: f32){
+        for constraint in self.constraints.iter() {
+            let (a, b) = constraint.particles();
+            let (start_x, start_y) = world_to_screen(self.display_position(a), scale_factor);
+            let (end_x, end_y) = world_to_screen(self.display_position(b), scale_factor);
+            match constraint {
+                Constraint::Spring { .. } => draw_line(start_x, start_y, end_x, end_y, 1.0, GRAY),
+                Constraint::Rod { .. } => draw_line(start_x, start_y, end_x, end_y, 2.0, WHITE),
+            }
+        }
+
         for i in 0..self.particles.len() {
-            let screen_x = self.particles[i].position[0] as f32 * scale_factor + screen_width() / 2.0; // Center the screen
-            let screen_y = self.particles[i].position[1] as f32 * scale_factor + screen_height() / 2.0;
+            let position = self.display_position(i);
+            let screen_x = position[0] as f32 * scale_factor + screen_width() / 2.0; // Center the screen
+            let screen_y = position[1] as f32 * scale_factor + screen_height() / 2.0;
             let color: Color = Color::new(0.00, 0.89, 0.19, 1.00);
 
             if self.do_collisions {
                 draw_circle(screen_x, screen_y, (self.particles[i].radius * scale_factor as f64) as f32, WHITE); // Draw particle
+            }else if self.particles[i].is_test() {
+                draw_circle(screen_x, screen_y, 1.0, GRAY); // Draw test particle
             }else{
-                draw_circle(screen_x, screen_y, 5.0 as f32, WHITE); // Draw particle
+                draw_circle(screen_x, screen_y, self.point_size, WHITE); // Draw particle
             }
-            if self.particles[i].name != "/".to_string() {
+            if self.particles[i].name != "/" {
                 draw_text(
-                    &format!("{}", self.particles[i].name),
+                    &self.particles[i].name,
                     screen_x + 10.0,
                     screen_y + 10.0,
                     16.0,
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use crate::structs::Model;
+
+const G_CONSTANT: f64 = 6.674e-11;
+const ASTRONOMICAL_UNIT: f64 = 1.495978707e11; // meters
+const DAY: f64 = 86400.0;                       // seconds
+const SOLAR_MASS: f64 = 1.989e30;               // kilograms
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Quantity {
+    Length,
+    Time,
+    Mass,
+    Velocity,
+}
+
+// Units a model's values are read and shown in; the model itself integrates in the units its G is given in,
+// SI for every model with G = 6.674e-11
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum UnitSystem {
+    Si,                                         // meters, seconds, kilograms
+    Astronomical,                               // astronomical units, days, solar masses
+    Henon { length: f64, mass: f64, time: f64 },// N-body units with G = 1, total mass 1 and energy -1/4, each one in model values
+    Model,                                      // values as the model holds them, such as the sandbox's own G
+}
+
+impl UnitSystem {
+    // Hénon units of a model, from its total mass and energy; model units if it is not bound
+    pub(crate) fn henon(model: &Model) -> Self {
+        let sources: Vec<_> = model.particles.iter().filter(|particle| !particle.is_test()).collect();
+        let mass: f64 = sources.iter().map(|particle| particle.mass).sum();
+        let mut energy = 0.0;
+        for (k, a) in sources.iter().enumerate() {
+            let velocity = a.velocity(model.delta_t);
+            energy += 0.5 * a.mass * (velocity[0].powi(2) + velocity[1].powi(2));
+            for b in &sources[k + 1..] {
+                let distance = (a.position[0] - b.position[0]).hypot(a.position[1] - b.position[1]);
+                energy -= model.g_constant * a.mass * b.mass / distance;
+            }
+        }
+        if !(energy < 0.0 && mass > 0.0) {
+            return UnitSystem::Model;
+        }
+        let length = model.g_constant * mass.powi(2) / (-4.0 * energy);
+        UnitSystem::Henon { length, mass, time: (length.powi(3) / (model.g_constant * mass)).sqrt() }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            UnitSystem::Si => "SI (m, s, kg)",
+            UnitSystem::Astronomical => "Astronomical (AU, days, solar masses)",
+            UnitSystem::Henon { .. } => "Henon N-body (G = M = 1, E = -1/4)",
+            UnitSystem::Model => "Model units",
+        }
+    }
+
+    // Whether G is 1 in a Hénon table for a model with the given G, i.e. time^2 = length^3 / (G mass); other units have no such condition
+    pub(crate) fn is_consistent_with(&self, g_constant: f64) -> bool {
+        match *self {
+            UnitSystem::Henon { length, mass, time } => (g_constant * mass * time.powi(2) / length.powi(3) - 1.0).abs() < 1e-6,
+            _ => true,
+        }
+    }
+
+    // Model value of one unit of the quantity
+    pub(crate) fn unit(&self, quantity: Quantity) -> f64 {
+        let (length, time, mass) = match *self {
+            UnitSystem::Si | UnitSystem::Model => (1.0, 1.0, 1.0),
+            UnitSystem::Astronomical => (ASTRONOMICAL_UNIT, DAY, SOLAR_MASS),
+            UnitSystem::Henon { length, mass, time } => (length, time, mass),
+        };
+        match quantity {
+            Quantity::Length => length,
+            Quantity::Time => time,
+            Quantity::Mass => mass,
+            Quantity::Velocity => length / time,
+        }
+    }
+
+    pub(crate) fn symbol(&self, quantity: Quantity) -> &'static str {
+        match (self, quantity) {
+            (UnitSystem::Model, _) => "",
+            (UnitSystem::Si, Quantity::Length) => "m",
+            (UnitSystem::Si, Quantity::Time) => "s",
+            (UnitSystem::Si, Quantity::Mass) => "kg",
+            (UnitSystem::Si, Quantity::Velocity) => "m/s",
+            (UnitSystem::Astronomical, Quantity::Length) => "AU",
+            (UnitSystem::Astronomical, Quantity::Time) => "d",
+            (UnitSystem::Astronomical, Quantity::Mass) => "Msun",
+            (UnitSystem::Astronomical, Quantity::Velocity) => "AU/d",
+            (UnitSystem::Henon { .. }, Quantity::Length) => "[L]",
+            (UnitSystem::Henon { .. }, Quantity::Time) => "[T]",
+            (UnitSystem::Henon { .. }, Quantity::Mass) => "[M]",
+            (UnitSystem::Henon { .. }, Quantity::Velocity) => "[L/T]",
+        }
+    }
+
+    // A value given in these units as a model value, and back
+    pub(crate) fn model_value(&self, value: f64, quantity: Quantity) -> f64 {
+        value * self.unit(quantity)
+    }
+
+    // A value of dimension length^l time^t mass^m given in these units as a model value, e.g. [1, -2, 1] for a force
+    pub(crate) fn model_value_of_dimension(&self, value: f64, [l, t, m]: [i32; 3]) -> f64 {
+        value * self.unit(Quantity::Length).powi(l) * self.unit(Quantity::Time).powi(t) * self.unit(Quantity::Mass).powi(m)
+    }
+
+    pub(crate) fn value_in(&self, value: f64, quantity: Quantity) -> f64 {
+        value / self.unit(quantity)
+    }
+
+    // G converted from model values into these units, or the other way round
+    pub(crate) fn gravitational_constant(&self, g_constant: f64) -> f64 {
+        g_constant * self.unit(Quantity::Mass) * self.unit(Quantity::Time).powi(2) / self.unit(Quantity::Length).powi(3)
+    }
+
+    pub(crate) fn gravitational_constant_to_model(&self, g_constant: f64) -> f64 {
+        g_constant / self.gravitational_constant(1.0)
+    }
+
+    // A model value in these units with its symbol, in scientific notation when very large or small
+    pub(crate) fn format(&self, value: f64, quantity: Quantity) -> String {
+        let value = self.value_in(value, quantity);
+        let number = if value == 0.0 || (1e-2..1e4).contains(&value.abs()) {
+            format!("{:.3}", value)
+        } else {
+            format!("{:.4e}", value)
+        };
+        match self.symbol(quantity) {
+            "" => number,
+            symbol => format!("{} {}", number, symbol),
+        }
+    }
+}
+
+// Units of a model that does not choose its own: SI if it uses the SI value of G
+pub(crate) fn default_units(g_constant: f64) -> UnitSystem {
+    if g_constant == G_CONSTANT { UnitSystem::Si } else { UnitSystem::Model }
+}
@@ -1,6 +1,9 @@
 use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
 use macroquad::prelude::{screen_height, screen_width};
 use crate::structs::*;
+use crate::fields::ForceField;
+use crate::interactions::ForceLaw;
 
 pub(crate) fn round_to_place(number: f64, place: u32) -> f64 {
     (number * (10_i32).pow(place) as f64).round() / 10_i32.pow(place) as f64
@@ -12,6 +15,28 @@ pub(crate) fn screen_to_world(mouse_pos: (f32, f32), scale_factor: f32) -> [f64;
     [world_x as f64, world_y as f64]
 }
 
+pub(crate) fn world_to_screen(position: [f64; 2], scale_factor: f32) -> (f32, f32) {
+    let screen_x = position[0] as f32 * scale_factor + screen_width() / 2.0;
+    let screen_y = position[1] as f32 * scale_factor + screen_height() / 2.0;
+    (screen_x, screen_y)
+}
+
+// Model name made safe for a file name
+pub(crate) fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+// First unused `<model name>-<n>.<extension>` in the directory
+pub(crate) fn next_free_path(directory: &Path, name: &str, extension: &str) -> PathBuf {
+    let stem = file_stem(name);
+    (1..)
+        .map(|n| directory.join(format!("{}-{}.{}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
 pub(crate) fn distance(point_a: [f64; 2], point_b: [f64; 2]) -> f64 {
     ((point_a[0] - point_b[0]).powi(2) + (point_a[1] - point_b[1]).powi(2)).sqrt()
 }
@@ -51,10 +76,11 @@ pub(crate) fn vector_to_components(vector: f64, theta: f64) -> [f64; 2] {
 
 pub(crate) fn check_collision(p1: &Particle, p2: &Particle) -> bool {
     let distance = distance(p1.position, p2.position);
-    distance <= (p1.radius + p2.radius) as f64
+    distance <= p1.radius + p2.radius
 }
 
-pub(crate) fn calculate_kinetic_energy(particles: &Vec<Particle>, delta_t: f64) -> f64 {
+#[allow(dead_code)]
+pub(crate) fn calculate_kinetic_energy(particles: &[Particle], delta_t: f64) -> f64 {
     let mut total_kinetic_energy = 0.0;
 
     for particle in particles.iter() {
@@ -69,23 +95,27 @@ pub(crate) fn calculate_kinetic_energy(particles: &Vec<Particle>, delta_t: f64)
     total_kinetic_energy
 }
 
-pub(crate) fn calculate_potential_energy(particles: &Vec<Particle>, g_constant: f64) -> f64 {
+#[allow(dead_code)]
+pub(crate) fn calculate_potential_energy(particles: &[Particle], force_law: &dyn ForceLaw, force_fields: &[ForceField], g_constant: f64) -> f64 {
     let mut total_potential_energy = 0.0;
 
     for i in 0..particles.len() {
         for j in (i + 1)..particles.len() {
             let distance = distance(particles[i].position, particles[j].position);
             if distance > 0.0 {
-                total_potential_energy += -g_constant * particles[i].mass * particles[j].mass / distance;
+                total_potential_energy += force_law.potential(g_constant, &particles[i], &particles[j]);
             }
         }
+        for field in force_fields.iter() {
+            total_potential_energy += field.potential(g_constant, &particles[i]);
+        }
     }
 
     total_potential_energy
 }
 
 
-pub(crate) fn calculate_linear_momentum(particles: &Vec<Particle>, delta_t: f64) -> [f64; 2] {
+pub(crate) fn calculate_linear_momentum(particles: &[Particle], delta_t: f64) -> [f64; 2] {
     let mut total_momentum = [0.0, 0.0];
 
     for particle in particles.iter() {
@@ -101,7 +131,8 @@ pub(crate) fn calculate_linear_momentum(particles: &Vec<Particle>, delta_t: f64)
 }
 
 
-pub(crate) fn calculate_angular_momentum(particles: &Vec<Particle>, delta_t: f64) -> f64 {
+#[allow(dead_code)]
+pub(crate) fn calculate_angular_momentum(particles: &[Particle], delta_t: f64) -> f64 {
     let mut total_mass = 0.0;
     let mut center_of_mass = [0.0, 0.0];
 
@@ -134,9 +165,10 @@ pub(crate) fn calculate_angular_momentum(particles: &Vec<Particle>, delta_t: f64
 }
 
 
-pub(crate) fn calculate_total_energy(particles: &Vec<Particle>, g_constant: f64, delta_t: f64) -> f64 {
+#[allow(dead_code)]
+pub(crate) fn calculate_total_energy(particles: &[Particle], force_law: &dyn ForceLaw, force_fields: &[ForceField], g_constant: f64, delta_t: f64) -> f64 {
     let kinetic_energy = calculate_kinetic_energy(particles, delta_t);
-    let potential_energy = calculate_potential_energy(particles, g_constant);
+    let potential_energy = calculate_potential_energy(particles, force_law, force_fields, g_constant);
 
     kinetic_energy + potential_energy
 }
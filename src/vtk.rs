@@ -0,0 +1,151 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use crate::structs::Model;
+use crate::units::{Quantity, UnitSystem};
+
+// A time series of snapshots for ParaView: one VTK XML polydata file (.vtp) per snapshot, kept in a directory
+// named after the collection (.pvd) that lists them by time. The collection is rewritten after every snapshot,
+// so it can be opened at any point of a run.
+pub(crate) struct VtkSeries {
+    pub(crate) path: PathBuf,               // the .pvd collection
+    directory: PathBuf,                     // holds the .vtp snapshots
+    stem: String,
+    units: UnitSystem,                      // display units of the model when the series started
+    snapshots: Vec<(f64, String)>,          // time and path relative to the collection of each snapshot
+}
+
+impl VtkSeries {
+    // ParaView only opens collections ending in .pvd, so the extension is added to paths without it; the
+    // snapshots' directory is the path without it, so it never clashes with the collection
+    pub(crate) fn create(path: &Path, model: &Model) -> io::Result<Self> {
+        let path = if path.extension().is_some_and(|extension| extension == "pvd") {
+            path.to_path_buf()
+        } else {
+            let mut name = path.as_os_str().to_owned();
+            name.push(".pvd");
+            PathBuf::from(name)
+        };
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| "snapshot".to_string());
+        let directory = path.with_extension("");
+        fs::create_dir_all(&directory)?;
+        Ok(VtkSeries {
+            path,
+            directory,
+            stem,
+            units: model.units,
+            snapshots: Vec::new(),
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    // Call after every step; only every Nth step is written
+    pub(crate) fn record(&mut self, model: &Model, every: u64) -> io::Result<()> {
+        if model.steps.is_multiple_of(every) {
+            self.write_snapshot(model)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn write_snapshot(&mut self, model: &Model) -> io::Result<()> {
+        let file = format!("{}_{:06}.vtp", self.stem, self.snapshots.len());
+        let mut writer = BufWriter::new(File::create(self.directory.join(&file))?);
+        write_polydata(&mut writer, model, self.units)?;
+        writer.flush()?;
+        self.snapshots.push((self.units.value_in(model.elapsed_time, Quantity::Time), format!("{}/{}", self.stem, file)));
+        self.write_collection()
+    }
+
+    fn write_collection(&self) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        writeln!(writer, "<?xml version=\"1.0\"?>")?;
+        writeln!(writer, "<VTKFile type=\"Collection\" version=\"0.1\" byte_order=\"LittleEndian\">")?;
+        writeln!(writer, "  <Collection>")?;
+        for (time, file) in &self.snapshots {
+            writeln!(writer, "    <DataSet timestep=\"{}\" group=\"\" part=\"0\" file=\"{}\"/>", time, xml_escape(file))?;
+        }
+        writeln!(writer, "  </Collection>")?;
+        writeln!(writer, "</VTKFile>")?;
+        writer.flush()
+    }
+}
+
+// Every particle as a vertex in the z = 0 plane, with mass, velocity, radius, name and index as point data,
+// all in the given units
+fn write_polydata(writer: &mut impl Write, model: &Model, units: UnitSystem) -> io::Result<()> {
+    let count = model.particles.len();
+    let length = |value| units.value_in(value, Quantity::Length);
+    let velocity = |value| units.value_in(value, Quantity::Velocity);
+    writeln!(writer, "<?xml version=\"1.0\"?>")?;
+    writeln!(writer, "<VTKFile type=\"PolyData\" version=\"0.1\" byte_order=\"LittleEndian\">")?;
+    writeln!(writer, "  <PolyData>")?;
+    writeln!(writer, "    <Piece NumberOfPoints=\"{}\" NumberOfVerts=\"{}\" NumberOfLines=\"0\" NumberOfStrips=\"0\" NumberOfPolys=\"0\">", count, count)?;
+
+    writeln!(writer, "      <PointData Scalars=\"mass\" Vectors=\"velocity\">")?;
+    write_array(writer, "Float64", "mass", 1, model.particles.iter().map(|particle| format!("{:e}", units.value_in(particle.mass, Quantity::Mass))))?;
+    write_array(writer, "Float64", "velocity", 3, model.particles.iter().map(|particle| {
+        let [vx, vy] = particle.velocity(model.delta_t);
+        format!("{:e} {:e} 0", velocity(vx), velocity(vy))
+    }))?;
+    write_array(writer, "Float64", "radius", 1, model.particles.iter().map(|particle| format!("{:e}", length(particle.radius))))?;
+    // ASCII string arrays are the bytes of each string as integers, each string ending in a zero
+    write_array(writer, "String", "name", 1, model.particles.iter().map(|particle| {
+        particle.name.bytes().chain([0]).map(|byte| byte.to_string()).collect::<Vec<_>>().join(" ")
+    }))?;
+    write_array(writer, "Int64", "id", 1, (0..count).map(|id| id.to_string()))?;
+    writeln!(writer, "      </PointData>")?;
+
+    writeln!(writer, "      <Points>")?;
+    write_array(writer, "Float64", "position", 3, model.particles.iter().map(|particle| {
+        format!("{:e} {:e} 0", length(particle.position[0]), length(particle.position[1]))
+    }))?;
+    writeln!(writer, "      </Points>")?;
+
+    writeln!(writer, "      <Verts>")?;
+    write_array(writer, "Int64", "connectivity", 1, (0..count).map(|id| id.to_string()))?;
+    write_array(writer, "Int64", "offsets", 1, (1..=count).map(|offset| offset.to_string()))?;
+    writeln!(writer, "      </Verts>")?;
+
+    writeln!(writer, "    </Piece>")?;
+    writeln!(writer, "  </PolyData>")?;
+    writeln!(writer, "</VTKFile>")
+}
+
+// One tuple of the array per line; floats are written in exponent form, which reads back exactly
+fn write_array(writer: &mut impl Write, kind: &str, name: &str, components: usize, values: impl Iterator<Item = String>) -> io::Result<()> {
+    writeln!(writer, "        <DataArray type=\"{}\" Name=\"{}\" NumberOfComponents=\"{}\" format=\"ascii\">", kind, name, components)?;
+    for value in values {
+        writeln!(writer, "          {}", value)?;
+    }
+    writeln!(writer, "        </DataArray>")
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::earth_moon_system;
+
+    #[test]
+    fn collection_without_pvd_extension_gets_one() {
+        let base = std::env::temp_dir().join(format!("vtk-{}", std::process::id()));
+        let model = earth_moon_system();
+        for (given, collection, directory) in [("run", "run.pvd", "run"), ("run.v2", "run.v2.pvd", "run.v2"), ("run.pvd", "run.pvd", "run")] {
+            let mut series = VtkSeries::create(&base.join(given), &model).unwrap();
+            series.write_snapshot(&model).unwrap();
+            assert_eq!(series.path, base.join(collection));
+            assert!(base.join(collection).is_file(), "{} is not a file", collection);
+            let listed = fs::read_to_string(base.join(collection)).unwrap();
+            let file = listed.split("file=\"").nth(1).and_then(|rest| rest.split('"').next()).unwrap().to_string();
+            assert!(file.starts_with(&format!("{}/", directory)), "{} lists {}", collection, file);
+            assert!(base.join(file).is_file(), "{} has no snapshot", directory);
+            fs::remove_dir_all(&base).unwrap();
+        }
+    }
+}